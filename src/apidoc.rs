@@ -11,12 +11,32 @@ use utoipa::OpenApi;
         (url = "http://localhost:8080", description = "Local dev")
     ),
     tags(
-        (name = "webhooks", description = "Incoming webhook endpoints")
+        (name = "webhooks", description = "Incoming webhook endpoints"),
+        (name = "chat", description = "Direct chat API for first-party apps"),
+        (name = "messages", description = "Proactive messages sent without a user turn"),
+        (name = "callbacks", description = "Replies from AI backends that work asynchronously"),
+        (name = "admin", description = "Bot control: pauses, access lists, in-flight chats and thread resets")
     ),
     // Handlers (paths)
     paths(
         crate::routes::waha::receive_waha,
         crate::routes::wacraft::receive_wacraft,
+        crate::routes::chat::chat,
+        crate::routes::chat::chat_stream,
+        crate::routes::webchat::ws_chat,
+        crate::routes::messages::send_message,
+        crate::routes::messages::schedule_message,
+        crate::routes::messages::list_scheduled,
+        crate::routes::messages::cancel_scheduled,
+        crate::routes::callbacks::ai_callback,
+        crate::routes::admin::list_pauses,
+        crate::routes::admin::pause,
+        crate::routes::admin::resume,
+        crate::routes::admin::list_access_rules,
+        crate::routes::admin::add_access_rule,
+        crate::routes::admin::remove_access_rule,
+        crate::routes::admin::list_locks,
+        crate::routes::admin::reset_thread,
     ),
     // Schemas used in requests/responses
     components(
@@ -24,7 +44,27 @@ use utoipa::OpenApi;
             crate::models::waha::WahaWebhook,
             crate::models::wacraft::WacraftWebhook,
             crate::models::ai::InputRequestDoc,
+            crate::models::ai::HistoryTurn,
             crate::models::ai::LlmApiResponse,
+            crate::models::chat::ChatRequest,
+            crate::models::chat::ChatAttachment,
+            crate::models::chat::ChatEvent,
+            crate::models::webchat::WebChatInbound,
+            crate::models::webchat::WebChatFrame,
+            crate::models::messages::OutboundMessageRequest,
+            crate::models::messages::OutboundContent,
+            crate::models::messages::OutboundMessageResponse,
+            crate::models::messages::ScheduleMessageRequest,
+            crate::models::messages::ScheduledMessage,
+            crate::models::ai::ScheduledReply,
+            crate::models::handoff::ChatPause,
+            crate::models::admin::PauseRequest,
+            crate::models::admin::LockInfo,
+            crate::models::access::AccessRule,
+            crate::models::access::AccessListKind,
+            crate::models::access::AccessRules,
+            crate::models::admin::ThreadResetRequest,
+            crate::models::admin::ThreadReset,
             crate::models::common::ErrorMessage
         )
     )
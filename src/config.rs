@@ -1,7 +1,11 @@
+use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::str::FromStr;
 
 use dotenvy::dotenv;
+use reqwest::Method;
+use serde::{Deserialize, Deserializer};
 use thiserror::Error;
 use url::Url;
 
@@ -20,16 +24,89 @@ pub struct Config {
     /// Optional Wacraft integration settings (requires base url, email, and password)
     pub wacraft: Option<WacraftConfig>,
 
-    /// AI base URL (e.g., http://localhost:8000)
-    pub ai_base_url: Url,
-    /// Path for the AI endpoint that receives user messages
-    /// Usually "/agent/messages/user"
-    pub ai_messages_user_path: String,
+    /// Optional Matrix bot settings (requires homeserver url and access token)
+    pub matrix: Option<MatrixConfig>,
+
+    /// Optional Signal settings via signal-cli-rest-api (requires api url and number)
+    pub signal: Option<SignalConfig>,
+
+    /// Bearer token for the first-party chat API (`/v1/chat`); the API is disabled when unset
+    pub chat_api_token: Option<String>,
+
+    /// Web-chat WebSocket channel (`/ws/chat`)
+    pub webchat: WebChatConfig,
+
+    /// Proactive messages API (`/v1/messages`)
+    pub messages_api: MessagesApiConfig,
+
+    /// Persistent scheduler for delayed messages (`/v1/messages/scheduled`)
+    pub scheduler: SchedulerConfig,
+
+    /// Pausing the bot per chat while a human operator takes over
+    pub handoff: HandoffConfig,
+
+    /// Bearer token for the admin API (`/admin`); the API is disabled when unset
+    pub admin_api_token: Option<String>,
+
+    /// Where pauses, thread resets, admin-managed access rules and consent records are kept
+    pub control_store: ControlStoreConfig,
+
+    /// Allow and block lists per provider and session, loaded from ACCESS_LISTS_PATH
+    pub access_lists: Vec<AccessListConfig>,
+
+    /// Token buckets limiting inbound messages per chat and per session
+    pub inbound_limits: InboundLimitConfig,
+
+    /// Opt-out and opt-in keywords
+    pub consent: ConsentConfig,
+
+    /// Pausing chats where the bot talks to another auto-responder
+    pub bot_loops: BotLoopConfig,
+
+    /// Signature and shared-secret checks of `/webhooks/waha` and `/webhooks/wacraft`
+    pub webhook_auth: WebhookAuthConfig,
+
+    /// Config-defined webhook providers, loaded from the JSON file at GENERIC_WEBHOOKS_PATH
+    pub generic_providers: Vec<GenericProviderConfig>,
+
+    /// Which AI backend answers user turns (`AI_BACKEND`, default `agent`)
+    pub ai_backend: AiBackendConfig,
+
+    /// Per-provider/session/number routing to other AI backends, loaded from AI_ROUTES_PATH
+    pub ai_routes: Vec<AiRouteConfig>,
+
+    /// Adapter-side conversation history for stateless backends
+    pub history: HistoryConfig,
+
+    /// Masking personal data in user content before it reaches the AI backend
+    pub redaction: RedactionConfig,
+
+    /// Checks of user messages before the AI call and of replies after it
+    pub moderation: ModerationConfig,
+
+    /// Timeouts, retries, circuit breaker and fallback reply for AI calls
+    pub ai_resilience: AiResilienceConfig,
+
+    /// Bearer token the agent must send to `/v1/callbacks/ai/{job_id}`; callbacks are disabled when unset
+    pub ai_callback_token: Option<String>,
+    /// How long a deferred (`202`) AI turn waits for its callback
+    pub ai_job_timeout_secs: u64,
+
+    /// Streamed replies are also cut after a sentence once a chunk has this many characters
+    pub ai_stream_min_chunk_chars: usize,
 
     /// Thread prefix for WAHA conversations (env), combined with user’s wa_id.
     pub thread_prefix_waha: String,
     /// Thread prefix for Wacraft conversations (env), combined with user’s wa_id.
     pub thread_prefix_wacraft: String,
+    /// Thread prefix for Matrix conversations (env), combined with the room id.
+    pub thread_prefix_matrix: String,
+    /// Thread prefix for Signal conversations (env), combined with the sender number or group id.
+    pub thread_prefix_signal: String,
+    /// Thread prefix for chat API conversations (env), combined with the app’s user_id.
+    pub thread_prefix_chat: String,
+    /// Thread prefix for web-chat conversations (env), combined with the session token.
+    pub thread_prefix_webchat: String,
 
     /// Agent knobs (read from env, forwarded to AI)
     pub chat_interface: String, // default "api"
@@ -64,11 +141,12 @@ impl Config {
         let waha_base_url = parse_url_required("WAHA_BASE_URL")?;
         let waha_api_key_plain = env::var("WAHA_API_KEY_PLAIN").ok();
 
-        let ai_base_url = parse_url_required("AI_BASE_URL")?;
-        let ai_messages_user_path = env_or_default("AI_MESSAGES_USER_PATH", "/agent/messages/user");
-
         let thread_prefix_waha = env_or_default("THREAD_PREFIX_WAHA", "waha:");
         let thread_prefix_wacraft = env_or_default("THREAD_PREFIX_WACRAFT", "wacraft:");
+        let thread_prefix_matrix = env_or_default("THREAD_PREFIX_MATRIX", "matrix:");
+        let thread_prefix_signal = env_or_default("THREAD_PREFIX_SIGNAL", "signal:");
+        let thread_prefix_chat = env_or_default("THREAD_PREFIX_CHAT", "chat:");
+        let thread_prefix_webchat = env_or_default("THREAD_PREFIX_WEBCHAT", "webchat:");
 
         // Agent knobs (match your agent’s docs)
         let chat_interface = env_or_default("CHAT_INTERFACE", "api");
@@ -85,10 +163,68 @@ impl Config {
             waha_base_url,
             waha_api_key_plain,
             wacraft: load_wacraft_config()?,
-            ai_base_url,
-            ai_messages_user_path,
+            matrix: load_matrix_config()?,
+            signal: load_signal_config()?,
+            chat_api_token: env::var("CHAT_API_TOKEN")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            webchat: WebChatConfig {
+                enabled: parse_bool_or_default("WEBCHAT_ENABLED", false)?,
+                session_ttl_secs: parse_or_default::<u64>("WEBCHAT_SESSION_TTL_SECS", 86_400)?,
+                buffer_size: parse_or_default::<usize>("WEBCHAT_BUFFER_SIZE", 50)?,
+            },
+            messages_api: MessagesApiConfig {
+                token: env::var("MESSAGES_API_TOKEN")
+                    .ok()
+                    .filter(|v| !v.trim().is_empty()),
+                rate_per_minute: parse_or_default::<u32>("MESSAGES_RATE_PER_MINUTE", 20)?,
+                rate_burst: parse_or_default::<u32>("MESSAGES_RATE_BURST", 5)?,
+            },
+            scheduler: SchedulerConfig {
+                enabled: parse_bool_or_default("SCHEDULER_ENABLED", false)?,
+                sqlite_path: env_or_default("SCHEDULER_SQLITE_PATH", "scheduler.db"),
+                poll_interval_secs: parse_or_default::<u64>("SCHEDULER_POLL_INTERVAL_SECS", 5)?,
+                max_attempts: parse_or_default::<u32>("SCHEDULER_MAX_ATTEMPTS", 3)?,
+                retry_delay_secs: parse_or_default::<u64>("SCHEDULER_RETRY_DELAY_SECS", 60)?,
+            },
+            handoff: load_handoff_config()?,
+            admin_api_token: env::var("ADMIN_API_TOKEN")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            control_store: load_control_store_config()?,
+            access_lists: load_access_lists()?,
+            inbound_limits: load_inbound_limit_config()?,
+            consent: load_consent_config()?,
+            bot_loops: load_bot_loop_config()?,
+            webhook_auth: load_webhook_auth_config()?,
+            generic_providers: load_generic_providers()?,
+            ai_backend: load_ai_backend_config()?,
+            ai_routes: load_ai_routes()?,
+            history: load_history_config()?,
+            redaction: load_redaction_config()?,
+            moderation: load_moderation_config()?,
+            ai_callback_token: env::var("AI_CALLBACK_TOKEN")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            ai_job_timeout_secs: parse_or_default::<u64>("AI_JOB_TIMEOUT_SECS", 900)?,
+            ai_stream_min_chunk_chars: parse_or_default::<usize>("AI_STREAM_MIN_CHUNK_CHARS", 120)?,
+            ai_resilience: AiResilienceConfig {
+                connect_timeout_ms: parse_or_default::<u64>("AI_CONNECT_TIMEOUT_MS", 5_000)?,
+                read_timeout_ms: parse_or_default::<u64>("AI_READ_TIMEOUT_MS", 60_000)?,
+                retries: parse_or_default::<u32>("AI_HTTP_RETRIES", 2)?,
+                retry_base_ms: parse_or_default::<u64>("AI_RETRY_BASE_MS", 200)?,
+                breaker_threshold: parse_or_default::<u32>("AI_BREAKER_THRESHOLD", 5)?,
+                breaker_cooldown_secs: parse_or_default::<u64>("AI_BREAKER_COOLDOWN_SECS", 30)?,
+                fallback_message: env::var("AI_FALLBACK_MESSAGE")
+                    .ok()
+                    .filter(|v| !v.trim().is_empty()),
+            },
             thread_prefix_waha,
             thread_prefix_wacraft,
+            thread_prefix_matrix,
+            thread_prefix_signal,
+            thread_prefix_chat,
+            thread_prefix_webchat,
             chat_interface,
             max_retries,
             loop_threshold,
@@ -138,6 +274,20 @@ fn parse_url_required(key: &'static str) -> Result<Url, ConfigError> {
     })
 }
 
+fn parse_url_optional(key: &'static str) -> Result<Option<Url>, ConfigError> {
+    match env::var(key) {
+        Ok(raw) if !raw.trim().is_empty() => {
+            Url::parse(&raw)
+                .map(Some)
+                .map_err(|_| ConfigError::InvalidUrl {
+                    name: key,
+                    value: raw,
+                })
+        }
+        _ => Ok(None),
+    }
+}
+
 fn parse_optional_i64(key: &'static str, raw: Option<String>) -> Result<Option<i64>, ConfigError> {
     match raw {
         Some(value) if !value.trim().is_empty() => value
@@ -148,6 +298,716 @@ fn parse_optional_i64(key: &'static str, raw: Option<String>) -> Result<Option<i
     }
 }
 
+fn parse_optional_f32(key: &'static str) -> Result<Option<f32>, ConfigError> {
+    match env::var(key) {
+        Ok(value) if !value.trim().is_empty() => value
+            .parse::<f32>()
+            .map(Some)
+            .map_err(|_| ConfigError::InvalidNumber { name: key, value }),
+        _ => Ok(None),
+    }
+}
+
+fn load_ai_backend_config() -> Result<AiBackendConfig, ConfigError> {
+    let kind = env_or_default("AI_BACKEND", "agent").to_lowercase();
+    match kind.as_str() {
+        "agent" => Ok(AiBackendConfig::Agent(AgentBackendConfig {
+            base_url: parse_url_required("AI_BASE_URL")?,
+            messages_user_path: env_or_default("AI_MESSAGES_USER_PATH", "/agent/messages/user"),
+            stream_path: env::var("AI_STREAM_PATH")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+        })),
+        "openai" => Ok(AiBackendConfig::Openai(OpenAiConfig {
+            base_url: parse_url_required("OPENAI_BASE_URL")?,
+            api_key: env::var("OPENAI_API_KEY")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            model: env::var("OPENAI_MODEL").map_err(|_| ConfigError::MissingVar("OPENAI_MODEL"))?,
+            system_prompt: env::var("OPENAI_SYSTEM_PROMPT")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            temperature: parse_optional_f32("OPENAI_TEMPERATURE")?,
+        })),
+        "dify" => Ok(AiBackendConfig::Dify(DifyConfig {
+            base_url: parse_url_required("DIFY_BASE_URL")?,
+            api_key: env::var("DIFY_API_KEY")
+                .map_err(|_| ConfigError::MissingVar("DIFY_API_KEY"))?,
+        })),
+        "flowise" => Ok(AiBackendConfig::Flowise(FlowiseConfig {
+            base_url: parse_url_required("FLOWISE_BASE_URL")?,
+            chatflow_id: env::var("FLOWISE_CHATFLOW_ID")
+                .map_err(|_| ConfigError::MissingVar("FLOWISE_CHATFLOW_ID"))?,
+            api_key: env::var("FLOWISE_API_KEY")
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+        })),
+        "rasa" => Ok(AiBackendConfig::Rasa(RasaConfig {
+            base_url: parse_url_required("RASA_BASE_URL")?,
+        })),
+        other => Err(ConfigError::Other(format!("Unknown AI_BACKEND: {other}"))),
+    }
+}
+
+/// Deserializable so the same shape can be reused in JSON config files (`"kind": "openai"`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiBackendConfig {
+    Agent(AgentBackendConfig),
+    Openai(OpenAiConfig),
+    Dify(DifyConfig),
+    Flowise(FlowiseConfig),
+    Rasa(RasaConfig),
+    /// Weighted A/B split, sticky per thread
+    Split {
+        variants: Vec<SplitVariantConfig>,
+    },
+    /// Answers with `primary`, mirrors requests to `candidate` and only logs its replies
+    Shadow {
+        primary: Box<AiBackendConfig>,
+        candidate: Box<AiBackendConfig>,
+    },
+}
+
+impl AiBackendConfig {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Split { variants } => {
+                if variants.iter().all(|v| v.weight == 0) {
+                    return Err("split needs at least one variant with a weight > 0".to_string());
+                }
+                variants.iter().try_for_each(|v| v.backend.validate())
+            }
+            Self::Shadow { primary, candidate } => {
+                primary.validate()?;
+                candidate.validate()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// OpenAI system prompts of this backend and the ones it composes.
+    pub fn system_prompts(&self) -> Vec<&str> {
+        match self {
+            Self::Openai(settings) => settings.system_prompt.as_deref().into_iter().collect(),
+            Self::Split { variants } => variants
+                .iter()
+                .flat_map(|v| v.backend.system_prompts())
+                .collect(),
+            Self::Shadow { primary, candidate } => {
+                let mut prompts = primary.system_prompts();
+                prompts.extend(candidate.system_prompts());
+                prompts
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SplitVariantConfig {
+    /// Used in logs
+    pub name: String,
+    /// Relative share of threads (e.g., 90 / 10)
+    pub weight: u32,
+    pub backend: AiBackendConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentBackendConfig {
+    /// AI base URL (e.g., http://localhost:8000)
+    pub base_url: Url,
+    /// Path for the AI endpoint that receives user messages
+    /// Usually "/agent/messages/user"
+    #[serde(default = "default_messages_user_path")]
+    pub messages_user_path: String,
+    /// Optional streaming variant of the same endpoint (SSE or NDJSON)
+    #[serde(default)]
+    pub stream_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAiConfig {
+    /// API root; `chat/completions` is appended (e.g., http://localhost:11434/v1)
+    pub base_url: Url,
+    pub api_key: Option<String>,
+    pub model: String,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DifyConfig {
+    /// Dify API root (e.g., https://api.dify.ai/v1)
+    pub base_url: Url,
+    /// App API key (`app-...`)
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlowiseConfig {
+    /// Flowise server (e.g., http://localhost:3000)
+    pub base_url: Url,
+    pub chatflow_id: String,
+    pub api_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RasaConfig {
+    /// Rasa server with the REST channel enabled (e.g., http://localhost:5005)
+    pub base_url: Url,
+}
+
+fn load_ai_routes() -> Result<Vec<AiRouteConfig>, ConfigError> {
+    let path = match env::var("AI_ROUTES_PATH") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Ok(Vec::new()),
+    };
+
+    let raw = fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Other(format!("Failed to read {path}: {err}")))?;
+    let routes: Vec<AiRouteConfig> = serde_json::from_str(&raw)
+        .map_err(|err| ConfigError::Other(format!("Invalid AI routes file {path}: {err}")))?;
+
+    for route in &routes {
+        if let Some(backend) = &route.backend {
+            backend
+                .validate()
+                .map_err(|err| ConfigError::Other(format!("AI route '{}': {err}", route.name)))?;
+        }
+    }
+
+    Ok(routes)
+}
+
+/// One entry of the AI routing table. Every field set in `match` must match.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AiRouteConfig {
+    /// Used in logs
+    pub name: String,
+    #[serde(rename = "match", default)]
+    pub matcher: AiRouteMatch,
+    /// Backend for matching conversations; the global `AI_BACKEND` when omitted
+    pub backend: Option<AiBackendConfig>,
+    #[serde(default)]
+    pub knobs: AgentKnobs,
+    /// Replaces the provider's thread prefix (keeps threads of different bots apart)
+    pub thread_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AiRouteMatch {
+    pub provider: Option<String>,
+    pub session: Option<String>,
+    pub messaging_product_id: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Per-route overrides of the agent knobs; unset fields fall back to the env values.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AgentKnobs {
+    pub chat_interface: Option<String>,
+    pub max_retries: Option<u32>,
+    pub loop_threshold: Option<u32>,
+    pub top_k: Option<u32>,
+    pub summarize_message_window: Option<u32>,
+    pub summarize_message_keep: Option<u32>,
+    pub summarize_system_messages: Option<bool>,
+}
+
+fn load_history_config() -> Result<HistoryConfig, ConfigError> {
+    // OpenAI-compatible APIs are stateless and useless without memory, so they get one by
+    // default. The agent, Dify, Flowise and Rasa keep their own conversation state.
+    let default_store = match env_or_default("AI_BACKEND", "agent")
+        .to_lowercase()
+        .as_str()
+    {
+        "openai" => "memory",
+        _ => "none",
+    };
+    let store = match env::var("HISTORY_STORE")
+        .unwrap_or_else(|_| default_store.to_string())
+        .to_lowercase()
+        .as_str()
+    {
+        "none" | "" => None,
+        "memory" => Some(HistoryStoreConfig::Memory),
+        "sqlite" => Some(HistoryStoreConfig::Sqlite {
+            path: env_or_default("HISTORY_SQLITE_PATH", "history.db"),
+        }),
+        other => {
+            return Err(ConfigError::Other(format!(
+                "Unknown HISTORY_STORE: {other}"
+            )));
+        }
+    };
+
+    let token_budget = match parse_or_default::<usize>("HISTORY_TOKEN_BUDGET", 0)? {
+        0 => None,
+        budget => Some(budget),
+    };
+
+    Ok(HistoryConfig {
+        store,
+        max_turns: parse_or_default::<usize>("HISTORY_MAX_TURNS", 20)?,
+        token_budget,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryConfig {
+    /// Where turns are kept; `None` leaves memory to the AI backend
+    pub store: Option<HistoryStoreConfig>,
+    /// Max stored messages replayed into each call
+    pub max_turns: usize,
+    /// Optional cap on the replayed history, in (estimated) tokens
+    pub token_budget: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AiResilienceConfig {
+    /// Max time to establish the connection to the AI backend
+    pub connect_timeout_ms: u64,
+    /// Max time between reads of the AI response (idle timeout, not the whole call)
+    pub read_timeout_ms: u64,
+    /// Extra attempts on connect errors (connect timeouts included) and 5xx; read timeouts
+    /// aren't retried
+    pub retries: u32,
+    /// Base of the exponential backoff between retries (full jitter)
+    pub retry_base_ms: u64,
+    /// Consecutive failed turns that open the circuit; 0 disables the breaker
+    pub breaker_threshold: u32,
+    /// How long an open circuit fails fast before a trial call is let through
+    pub breaker_cooldown_secs: u64,
+    /// Sent to the user when the AI call still fails; the turn is dropped when unset
+    pub fallback_message: Option<String>,
+}
+
+fn load_handoff_config() -> Result<HandoffConfig, ConfigError> {
+    Ok(HandoffConfig {
+        detect_operator: parse_bool_or_default("HANDOFF_DETECT_OPERATOR", false)?,
+        pause_secs: parse_or_default::<u64>("HANDOFF_PAUSE_SECS", 3_600)?,
+        resume_command: env_or_default("HANDOFF_RESUME_COMMAND", "#bot")
+            .trim()
+            .to_string(),
+        sync_operator: parse_bool_or_default("HANDOFF_SYNC_OPERATOR", false)?,
+        operator_chat_interface: env_or_default("HANDOFF_OPERATOR_CHAT_INTERFACE", "operator"),
+    })
+}
+
+fn load_control_store_config() -> Result<ControlStoreConfig, ConfigError> {
+    match env_or_default("CONTROL_STORE", "memory")
+        .to_lowercase()
+        .as_str()
+    {
+        "memory" | "" => Ok(ControlStoreConfig::Memory),
+        "sqlite" => Ok(ControlStoreConfig::Sqlite {
+            path: env_or_default("CONTROL_SQLITE_PATH", "control.db"),
+        }),
+        other => Err(ConfigError::Other(format!(
+            "Unknown CONTROL_STORE: {other}"
+        ))),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HandoffConfig {
+    /// Pause a chat when a message we didn't send shows up as ours (WAHA `fromMe`, Wacraft outbound)
+    pub detect_operator: bool,
+    /// How long a handoff pauses the chat; 0 pauses until resumed
+    pub pause_secs: u64,
+    /// Operator message that resumes the bot in its chat (the message itself is not answered)
+    pub resume_command: String,
+    /// Forward operator messages (WAHA `fromMe`, Wacraft outbound) to the agent as context
+    pub sync_operator: bool,
+    /// `chat_interface` of those context messages; the agent must not answer them
+    pub operator_chat_interface: String,
+}
+
+fn load_inbound_limit_config() -> Result<InboundLimitConfig, ConfigError> {
+    Ok(InboundLimitConfig {
+        chat_per_minute: parse_or_default::<u32>("INBOUND_RATE_PER_MINUTE", 0)?,
+        chat_burst: parse_or_default::<u32>("INBOUND_RATE_BURST", 5)?,
+        session_per_minute: parse_or_default::<u32>("INBOUND_SESSION_RATE_PER_MINUTE", 0)?,
+        session_burst: parse_or_default::<u32>("INBOUND_SESSION_RATE_BURST", 50)?,
+        slow_down_message: env::var("INBOUND_SLOW_DOWN_MESSAGE")
+            .ok()
+            .filter(|v| !v.trim().is_empty()),
+        block_after: parse_or_default::<u32>("INBOUND_BLOCK_AFTER", 0)?,
+        block_window_secs: parse_or_default::<u64>("INBOUND_BLOCK_WINDOW_SECS", 300)?,
+        block_secs: parse_or_default::<u64>("INBOUND_BLOCK_SECS", 900)?,
+    })
+}
+
+/// A rate of 0 turns its limit off.
+#[derive(Debug, Clone)]
+pub struct InboundLimitConfig {
+    /// Sustained messages allowed per chat
+    pub chat_per_minute: u32,
+    /// Messages a chat can send at once before the per-minute rate applies
+    pub chat_burst: u32,
+    /// Sustained messages allowed per provider and session, across all of its chats
+    pub session_per_minute: u32,
+    pub session_burst: u32,
+    /// Sent once when a chat goes over its limit; over-limit messages are dropped silently when unset
+    pub slow_down_message: Option<String>,
+    /// Over-limit messages within `block_window_secs` that get a chat blocked; 0 never blocks
+    pub block_after: u32,
+    pub block_window_secs: u64,
+    /// How long a blocked chat's messages are dropped
+    pub block_secs: u64,
+}
+
+fn load_consent_config() -> Result<ConsentConfig, ConfigError> {
+    let keywords = |key: &'static str, default: &'static str| -> Vec<String> {
+        env_or_default(key, default)
+            .split(',')
+            .map(|keyword| keyword.trim().to_uppercase())
+            .filter(|keyword| !keyword.is_empty())
+            .collect()
+    };
+    // An empty reply turns the confirmation off.
+    let reply = |key: &'static str, default: &'static str| {
+        Some(env_or_default(key, default)).filter(|v| !v.trim().is_empty())
+    };
+    Ok(ConsentConfig {
+        enabled: parse_bool_or_default("CONSENT_ENABLED", false)?,
+        opt_out_keywords: keywords(
+            "CONSENT_OPT_OUT_KEYWORDS",
+            "STOP,UNSUBSCRIBE,CANCEL,SAIR,PARAR,CANCELAR,BAJA",
+        ),
+        opt_in_keywords: keywords("CONSENT_OPT_IN_KEYWORDS", "START,SUBSCRIBE,VOLTAR,ALTA"),
+        opt_out_reply: reply(
+            "CONSENT_OPT_OUT_REPLY",
+            "You won't get any more messages from us. Send START to subscribe again.",
+        ),
+        opt_in_reply: reply(
+            "CONSENT_OPT_IN_REPLY",
+            "You're subscribed again. Send STOP to unsubscribe.",
+        ),
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsentConfig {
+    pub enabled: bool,
+    /// Uppercased; a message consisting of just one of these opts the contact out
+    pub opt_out_keywords: Vec<String>,
+    pub opt_in_keywords: Vec<String>,
+    /// Confirmation sent after an opt-out; none when unset
+    pub opt_out_reply: Option<String>,
+    pub opt_in_reply: Option<String>,
+}
+
+fn load_bot_loop_config() -> Result<BotLoopConfig, ConfigError> {
+    Ok(BotLoopConfig {
+        enabled: parse_bool_or_default("BOT_LOOP_DETECTION", false)?,
+        window_secs: parse_or_default::<u64>("BOT_LOOP_WINDOW_SECS", 60)?,
+        max_messages: parse_or_default::<u32>("BOT_LOOP_MAX_MESSAGES", 10)?,
+        max_repeats: parse_or_default::<u32>("BOT_LOOP_MAX_REPEATS", 3)?,
+        min_reply_ms: parse_or_default::<u64>("BOT_LOOP_MIN_REPLY_MS", 1_500)?,
+        max_fast_replies: parse_or_default::<u32>("BOT_LOOP_MAX_FAST_REPLIES", 3)?,
+        pause_secs: parse_or_default::<u64>("BOT_LOOP_PAUSE_SECS", 3_600)?,
+        alert_url: parse_url_optional("BOT_LOOP_ALERT_URL")?,
+    })
+}
+
+/// Each check is off when its threshold is 0.
+#[derive(Debug, Clone)]
+pub struct BotLoopConfig {
+    pub enabled: bool,
+    pub window_secs: u64,
+    /// Inbound messages within `window_secs` that look like a loop
+    pub max_messages: u32,
+    /// Same (or nearly the same) inbound message this many times in a row
+    pub max_repeats: u32,
+    /// Messages arriving sooner than this after our reply are too fast for a human
+    pub min_reply_ms: u64,
+    /// That many too-fast messages in a row
+    pub max_fast_replies: u32,
+    /// How long a loop pauses the chat; 0 pauses until resumed
+    pub pause_secs: u64,
+    /// Gets a JSON `LoopAlert` when a loop is detected
+    pub alert_url: Option<Url>,
+}
+
+fn load_webhook_auth_config() -> Result<WebhookAuthConfig, ConfigError> {
+    let secret = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let config = WebhookAuthConfig {
+        // WAHA signs with HMAC-SHA512 only.
+        waha_hmac: secret("WAHA_WEBHOOK_HMAC_SECRET").map(|secret| HmacConfig {
+            secret,
+            header: "X-Webhook-Hmac".to_string(),
+            algorithm: "sha512".to_string(),
+            prefix: None,
+        }),
+        wacraft_secret: secret("WACRAFT_WEBHOOK_SECRET").map(|secret| SharedSecretConfig {
+            secret,
+            header: env_or_default("WACRAFT_WEBHOOK_SECRET_HEADER", "X-Webhook-Secret"),
+        }),
+        wacraft_hmac: secret("WACRAFT_WEBHOOK_HMAC_SECRET").map(|secret| HmacConfig {
+            secret,
+            header: env_or_default("WACRAFT_WEBHOOK_HMAC_HEADER", "X-Webhook-Hmac"),
+            algorithm: env_or_default("WACRAFT_WEBHOOK_HMAC_ALGORITHM", "sha256"),
+            prefix: env::var("WACRAFT_WEBHOOK_HMAC_PREFIX")
+                .ok()
+                .filter(|v| !v.is_empty()),
+        }),
+    };
+    if let Some(hmac) = &config.wacraft_hmac {
+        validate_hmac_algorithm("WACRAFT_WEBHOOK_HMAC_ALGORITHM", hmac)?;
+    }
+    Ok(config)
+}
+
+/// Webhook checks are off while their secret is unset; when both Wacraft checks are set,
+/// both must pass.
+#[derive(Debug, Clone)]
+pub struct WebhookAuthConfig {
+    pub waha_hmac: Option<HmacConfig>,
+    pub wacraft_secret: Option<SharedSecretConfig>,
+    pub wacraft_hmac: Option<HmacConfig>,
+}
+
+/// A static secret the sender puts in a header.
+#[derive(Debug, Clone)]
+pub struct SharedSecretConfig {
+    pub secret: String,
+    pub header: String,
+}
+
+const PII_DETECTORS: [&str; 5] = ["cpf", "cnpj", "card", "email", "phone"];
+
+fn load_redaction_config() -> Result<RedactionConfig, ConfigError> {
+    let detectors: Vec<String> = env_or_default("PII_REDACT", "")
+        .split(',')
+        .map(|d| d.trim().to_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect();
+    if let Some(unknown) = detectors
+        .iter()
+        .find(|d| !PII_DETECTORS.contains(&d.as_str()))
+    {
+        return Err(ConfigError::Other(format!(
+            "Unknown PII detector '{unknown}' (expected one of {})",
+            PII_DETECTORS.join(", ")
+        )));
+    }
+
+    let patterns = match env::var("PII_PATTERNS_PATH") {
+        Ok(path) if !path.trim().is_empty() => {
+            let raw = fs::read_to_string(&path)
+                .map_err(|err| ConfigError::Other(format!("Failed to read {path}: {err}")))?;
+            serde_json::from_str::<Vec<PiiPatternConfig>>(&raw).map_err(|err| {
+                ConfigError::Other(format!("Invalid PII patterns file {path}: {err}"))
+            })?
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(RedactionConfig {
+        detectors,
+        patterns,
+        reversible: parse_bool_or_default("PII_REVERSIBLE", true)?,
+        token_key: env::var("PII_TOKEN_KEY")
+            .ok()
+            .filter(|v| !v.trim().is_empty()),
+    })
+}
+
+/// Redaction is off while no detector or pattern is configured.
+#[derive(Debug, Clone)]
+pub struct RedactionConfig {
+    /// Built-in detectors: `cpf`, `cnpj`, `card`, `email`, `phone`
+    pub detectors: Vec<String>,
+    /// Custom patterns, loaded from PII_PATTERNS_PATH
+    pub patterns: Vec<PiiPatternConfig>,
+    /// Replace values with per-thread tokens (`[EMAIL_1f0c9a2b]`) that are put back into the
+    /// reply; otherwise they are masked for good (`[EMAIL]`)
+    pub reversible: bool,
+    /// Key of the HMAC tokens are derived from; a random one per process when unset
+    pub token_key: Option<String>,
+}
+
+/// A custom redaction pattern (see `PII_PATTERNS_PATH`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct PiiPatternConfig {
+    /// Label of the token, uppercased (`order_id` → `[ORDER_ID_5c1e0b7d]`)
+    pub name: String,
+    /// Regular expression (Rust `regex` syntax)
+    pub pattern: String,
+}
+
+fn load_moderation_config() -> Result<ModerationConfig, ConfigError> {
+    let (inbound, outbound) = match env::var("MODERATION_PATH") {
+        Ok(path) if !path.trim().is_empty() => {
+            let raw = fs::read_to_string(&path)
+                .map_err(|err| ConfigError::Other(format!("Failed to read {path}: {err}")))?;
+            let file: ModerationFile = serde_json::from_str(&raw).map_err(|err| {
+                ConfigError::Other(format!("Invalid moderation file {path}: {err}"))
+            })?;
+            (file.inbound, file.outbound)
+        }
+        _ => (None, None),
+    };
+
+    if let Some(rules) = &inbound {
+        if rules.action == ModerationAction::Rewrite
+            || rules.allowed_domains.is_some()
+            || !rules.leak_markers.is_empty()
+        {
+            return Err(ConfigError::Other(
+                "Inbound moderation only blocks: `action: rewrite`, `allowed_domains` and `leak_markers` apply to replies"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let http = parse_url_optional("MODERATION_URL")?
+        .map(|url| -> Result<_, ConfigError> {
+            Ok(ModerationHttpConfig {
+                url,
+                api_key: env::var("MODERATION_API_KEY")
+                    .ok()
+                    .filter(|v| !v.trim().is_empty()),
+                timeout_ms: parse_or_default::<u64>("MODERATION_TIMEOUT_MS", 5_000)?,
+            })
+        })
+        .transpose()?;
+    if http.is_none() && inbound.iter().chain(&outbound).any(|rules| rules.http) {
+        return Err(ConfigError::MissingVar("MODERATION_URL"));
+    }
+
+    Ok(ModerationConfig {
+        inbound,
+        outbound,
+        http,
+        fail_closed: parse_bool_or_default("MODERATION_FAIL_CLOSED", false)?,
+    })
+}
+
+/// Moderation is off for a direction without rules.
+#[derive(Debug, Clone)]
+pub struct ModerationConfig {
+    /// Checks of user messages, loaded from MODERATION_PATH
+    pub inbound: Option<ModerationRules>,
+    /// Checks of AI replies, loaded from MODERATION_PATH
+    pub outbound: Option<ModerationRules>,
+    /// Moderation endpoint used by rules with `http: true`
+    pub http: Option<ModerationHttpConfig>,
+    /// Treat a failed call to the endpoint as flagged instead of letting the text through
+    pub fail_closed: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ModerationFile {
+    inbound: Option<ModerationRules>,
+    outbound: Option<ModerationRules>,
+}
+
+/// The checks of one direction (see `MODERATION_PATH`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationRules {
+    /// Whole words or phrases, case-insensitive
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Regular expressions (Rust `regex` syntax)
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Replies only: links must point to one of these domains or their subdomains; links
+    /// aren't checked when omitted
+    pub allowed_domains: Option<Vec<String>>,
+    /// Replies only: phrases that only appear in the system prompt
+    #[serde(default)]
+    pub leak_markers: Vec<String>,
+    /// Also ask the moderation endpoint (`MODERATION_URL`)
+    #[serde(default)]
+    pub http: bool,
+    #[serde(default)]
+    pub action: ModerationAction,
+    /// Sent instead of a blocked message; nothing is sent when unset
+    pub reply: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    /// Replace the whole message with `reply`
+    #[default]
+    Block,
+    /// Replies only: mask what was flagged and send the rest; flags that can't be masked
+    /// still block
+    Rewrite,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModerationHttpConfig {
+    pub url: Url,
+    /// Sent as a bearer token
+    pub api_key: Option<String>,
+    pub timeout_ms: u64,
+}
+
+fn load_access_lists() -> Result<Vec<AccessListConfig>, ConfigError> {
+    let path = match env::var("ACCESS_LISTS_PATH") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Ok(Vec::new()),
+    };
+
+    let raw = fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Other(format!("Failed to read {path}: {err}")))?;
+    let lists: Vec<AccessListConfig> = serde_json::from_str(&raw)
+        .map_err(|err| ConfigError::Other(format!("Invalid access lists file {path}: {err}")))?;
+
+    for list in &lists {
+        if list
+            .allow
+            .iter()
+            .chain(&list.deny)
+            .any(|p| p.trim().is_empty())
+        {
+            return Err(ConfigError::Other(format!(
+                "Access list of '{}' has an empty pattern",
+                list.provider
+            )));
+        }
+    }
+
+    Ok(lists)
+}
+
+/// Allow and block lists of one provider, and optionally one session. Patterns match chat
+/// ids; `*` stands for any run of characters (`5511*`, `*@g.us`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccessListConfig {
+    pub provider: String,
+    /// WAHA session or Wacraft messaging product; every session when omitted
+    pub session: Option<String>,
+    /// When any allow pattern applies, only matching chats are answered
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Matching chats are never answered
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+/// Where bot control state (pauses, thread resets, admin-managed access rules, consent) is kept.
+#[derive(Debug, Clone)]
+pub enum ControlStoreConfig {
+    Memory,
+    Sqlite { path: String },
+}
+
+#[derive(Debug, Clone)]
+pub enum HistoryStoreConfig {
+    Memory,
+    Sqlite { path: String },
+}
+
+fn default_messages_user_path() -> String {
+    "/agent/messages/user".to_string()
+}
+
 fn load_wacraft_config() -> Result<Option<WacraftConfig>, ConfigError> {
     let base_raw = match env::var("WACRAFT_BASE_URL") {
         Ok(v) if !v.trim().is_empty() => v,
@@ -190,3 +1050,289 @@ pub struct WacraftConfig {
     pub refresh_token: Option<String>,
     pub token_expires_at: Option<i64>,
 }
+
+fn load_matrix_config() -> Result<Option<MatrixConfig>, ConfigError> {
+    let homeserver_raw = match env::var("MATRIX_HOMESERVER_URL") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Ok(None),
+    };
+
+    let homeserver_url = Url::parse(&homeserver_raw).map_err(|_| ConfigError::InvalidUrl {
+        name: "MATRIX_HOMESERVER_URL",
+        value: homeserver_raw.clone(),
+    })?;
+
+    let access_token = env::var("MATRIX_ACCESS_TOKEN")
+        .map_err(|_| ConfigError::MissingVar("MATRIX_ACCESS_TOKEN"))?;
+    let user_id = env::var("MATRIX_USER_ID")
+        .ok()
+        .filter(|v| !v.trim().is_empty());
+
+    Ok(Some(MatrixConfig {
+        homeserver_url,
+        access_token,
+        user_id,
+        sync_timeout_ms: parse_or_default::<u64>("MATRIX_SYNC_TIMEOUT_MS", 30_000)?,
+        sync_token_path: env_or_default("MATRIX_SYNC_TOKEN_PATH", "matrix_sync_token"),
+        auto_join: parse_bool_or_default("MATRIX_AUTO_JOIN", true)?,
+        typing: parse_bool_or_default("MATRIX_TYPING", true)?,
+        send_seen: parse_bool_or_default("MATRIX_SEND_SEEN", true)?,
+    }))
+}
+
+#[derive(Debug, Clone)]
+pub struct MatrixConfig {
+    pub homeserver_url: Url,
+    pub access_token: String,
+    /// Bot user id (e.g., @bot:example.org). Resolved via `/account/whoami` when unset.
+    pub user_id: Option<String>,
+    /// Long-poll timeout for `/sync`, in milliseconds
+    pub sync_timeout_ms: u64,
+    /// File where the last `next_batch` token is persisted between restarts
+    pub sync_token_path: String,
+    /// Join rooms automatically when the bot is invited
+    pub auto_join: bool,
+    /// Send typing notifications while the AI is working
+    pub typing: bool,
+    /// Send read receipts for processed events
+    pub send_seen: bool,
+}
+
+fn load_signal_config() -> Result<Option<SignalConfig>, ConfigError> {
+    let api_raw = match env::var("SIGNAL_API_URL") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Ok(None),
+    };
+
+    let api_url = Url::parse(&api_raw).map_err(|_| ConfigError::InvalidUrl {
+        name: "SIGNAL_API_URL",
+        value: api_raw.clone(),
+    })?;
+
+    let number = env::var("SIGNAL_NUMBER").map_err(|_| ConfigError::MissingVar("SIGNAL_NUMBER"))?;
+
+    Ok(Some(SignalConfig {
+        api_url,
+        number,
+        receive_timeout_secs: parse_or_default::<u64>("SIGNAL_RECEIVE_TIMEOUT_SECS", 10)?,
+        typing: parse_bool_or_default("SIGNAL_TYPING", true)?,
+        send_seen: parse_bool_or_default("SIGNAL_SEND_SEEN", true)?,
+    }))
+}
+
+#[derive(Debug, Clone)]
+pub struct SignalConfig {
+    /// signal-cli-rest-api base URL (e.g., http://localhost:8081)
+    pub api_url: Url,
+    /// Registered account number the bot receives and sends as (e.g., +5511912345678)
+    pub number: String,
+    /// How long a single `/v1/receive` poll waits for new messages, in seconds
+    pub receive_timeout_secs: u64,
+    /// Send typing indicators while the AI is working
+    pub typing: bool,
+    /// Send read receipts for processed messages
+    pub send_seen: bool,
+}
+
+fn load_generic_providers() -> Result<Vec<GenericProviderConfig>, ConfigError> {
+    let path = match env::var("GENERIC_WEBHOOKS_PATH") {
+        Ok(v) if !v.trim().is_empty() => v,
+        _ => return Ok(Vec::new()),
+    };
+
+    let raw = fs::read_to_string(&path)
+        .map_err(|err| ConfigError::Other(format!("Failed to read {path}: {err}")))?;
+    let providers: Vec<GenericProviderConfig> = serde_json::from_str(&raw).map_err(|err| {
+        ConfigError::Other(format!("Invalid generic webhooks file {path}: {err}"))
+    })?;
+
+    let mut names = std::collections::HashSet::new();
+    let mut paths = std::collections::HashSet::new();
+    for provider in &providers {
+        // The name keys routes, access lists and pauses, so it can't pass for another provider.
+        if RESERVED_PROVIDER_NAMES.contains(&provider.name.as_str()) {
+            return Err(ConfigError::Other(format!(
+                "Generic provider name '{}' is reserved for a built-in provider",
+                provider.name
+            )));
+        }
+        if !names.insert(provider.name.as_str()) {
+            return Err(ConfigError::Other(format!(
+                "Generic provider name '{}' is used more than once",
+                provider.name
+            )));
+        }
+        if !provider.path.starts_with('/') {
+            return Err(ConfigError::Other(format!(
+                "Generic provider '{}' route path must start with '/': {}",
+                provider.name, provider.path
+            )));
+        }
+        // Clashing routes would make axum panic when the router is built.
+        let path = provider.path.trim_end_matches('/');
+        if RESERVED_PATHS.contains(&path)
+            || RESERVED_PATH_PREFIXES
+                .iter()
+                .any(|prefix| path == prefix.trim_end_matches('/') || path.starts_with(prefix))
+        {
+            return Err(ConfigError::Other(format!(
+                "Generic provider '{}' route path is reserved: {}",
+                provider.name, provider.path
+            )));
+        }
+        if !paths.insert(path.to_string()) {
+            return Err(ConfigError::Other(format!(
+                "Generic provider '{}' reuses route path {}",
+                provider.name, provider.path
+            )));
+        }
+        let fields = &provider.fields;
+        for expr in [
+            Some(&fields.chat_id),
+            fields.message_id.as_ref(),
+            fields.text.as_ref(),
+            fields.message_type.as_ref(),
+            fields.from_me.as_ref(),
+            fields.timestamp.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if crate::services::generic::field_pointer(expr).is_none() {
+                return Err(ConfigError::Other(format!(
+                    "Generic provider '{}' has an unsupported field expression: {expr}",
+                    provider.name
+                )));
+            }
+        }
+        if let Some(hmac) = &provider.hmac {
+            validate_hmac_algorithm(&format!("generic provider '{}'", provider.name), hmac)?;
+        }
+    }
+
+    Ok(providers)
+}
+
+// Served by the adapter itself; config-defined providers can't take them.
+const RESERVED_PROVIDER_NAMES: [&str; 6] =
+    ["waha", "wacraft", "matrix", "signal", "chat", "webchat"];
+const RESERVED_PATHS: [&str; 2] = ["/webhooks/waha", "/webhooks/wacraft"];
+const RESERVED_PATH_PREFIXES: [&str; 5] = ["/v1/", "/ws/", "/admin/", "/docs/", "/api-docs/"];
+
+const HMAC_ALGORITHMS: [&str; 2] = ["sha256", "sha512"];
+
+fn validate_hmac_algorithm(owner: &str, hmac: &HmacConfig) -> Result<(), ConfigError> {
+    if HMAC_ALGORITHMS.contains(&hmac.algorithm.to_ascii_lowercase().as_str()) {
+        return Ok(());
+    }
+    Err(ConfigError::Other(format!(
+        "Unknown HMAC algorithm '{}' for {owner} (expected one of {})",
+        hmac.algorithm,
+        HMAC_ALGORITHMS.join(", ")
+    )))
+}
+
+#[derive(Debug, Clone)]
+pub struct WebChatConfig {
+    pub enabled: bool,
+    /// Idle sessions (no socket attached) are forgotten after this many seconds
+    pub session_ttl_secs: u64,
+    /// Max frames buffered per session while no socket is attached
+    pub buffer_size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessagesApiConfig {
+    /// Bearer token for `/v1/messages`; the API is disabled when unset
+    pub token: Option<String>,
+    /// Sustained sends allowed per recipient
+    pub rate_per_minute: u32,
+    /// Sends a recipient can take at once before the per-minute rate applies
+    pub rate_burst: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    pub enabled: bool,
+    /// SQLite file holding the pending messages
+    pub sqlite_path: String,
+    /// How often due messages are looked up
+    pub poll_interval_secs: u64,
+    /// Failed sends are retried until a message has been tried this many times
+    pub max_attempts: u32,
+    /// Wait before retrying a failed send
+    pub retry_delay_secs: u64,
+}
+
+/// A webhook provider defined entirely in config (see `GENERIC_WEBHOOKS_PATH`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenericProviderConfig {
+    /// Unique name, used in logs and as the default thread prefix (`<name>:`)
+    pub name: String,
+    /// Route the webhook is served on (e.g., /webhooks/helpdesk)
+    pub path: String,
+    pub thread_prefix: Option<String>,
+    pub fields: GenericFieldMapping,
+    /// Values of `fields.message_type` treated as text. Defaults to `["text"]`.
+    #[serde(default = "default_text_types")]
+    pub text_types: Vec<String>,
+    pub hmac: Option<HmacConfig>,
+    pub reply: Option<GenericReplyTemplate>,
+}
+
+/// JSON-pointer (`/message/text`) or simple JSONPath (`$.message.text`) expressions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenericFieldMapping {
+    pub chat_id: String,
+    pub message_id: Option<String>,
+    pub text: Option<String>,
+    pub message_type: Option<String>,
+    pub from_me: Option<String>,
+    pub timestamp: Option<String>,
+}
+
+/// HMAC signature of the raw request body, sent hex-encoded in a header.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HmacConfig {
+    pub secret: String,
+    /// Header carrying the hex signature (e.g., X-Signature)
+    pub header: String,
+    /// `sha256` (default) or `sha512`
+    #[serde(default = "default_hmac_algorithm")]
+    pub algorithm: String,
+    /// Optional prefix stripped from the header value (e.g., `sha256=`)
+    pub prefix: Option<String>,
+}
+
+/// Outbound reply request. `{{chat_id}}`, `{{message_id}}` and `{{text}}` are replaced in the
+/// url, header values and every string of the body.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenericReplyTemplate {
+    pub url: String,
+    #[serde(
+        default = "default_reply_method",
+        deserialize_with = "deserialize_method"
+    )]
+    pub method: Method,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub body: Option<serde_json::Value>,
+}
+
+fn default_text_types() -> Vec<String> {
+    vec!["text".to_string()]
+}
+
+fn default_hmac_algorithm() -> String {
+    "sha256".to_string()
+}
+
+fn default_reply_method() -> Method {
+    Method::POST
+}
+
+fn deserialize_method<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Method, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    Method::from_bytes(raw.to_uppercase().as_bytes())
+        .map_err(|_| serde::de::Error::custom(format!("invalid reply method '{raw}'")))
+}
@@ -0,0 +1,115 @@
+use super::AiTurn;
+use crate::{
+    AppState,
+    models::{
+        ai::LlmApiResponse,
+        chat::{ChatEvent, ChatRequest},
+    },
+    services::ai::{AiError, router::RouteKey},
+    utils::thread_id_for_chat,
+};
+use chrono::Utc;
+use serde_json::json;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug, Error)]
+pub enum ChatHandleError {
+    #[error("ai call failed: {0}")]
+    Ai(AiError),
+    #[error("message has neither text nor attachments")]
+    EmptyMessage,
+    #[error("AI replies are paused for this chat")]
+    Paused,
+    #[error("too many messages, slow down")]
+    RateLimited,
+}
+
+/// Runs one chat turn for a first-party app. When `events` is set, typing/status frames are
+/// emitted while the turn is processed (the final reply is left to the caller). A turn the
+/// agent accepts as a background job is waited for until its callback arrives.
+pub async fn handle_chat(
+    state: &AppState,
+    request: ChatRequest,
+    events: Option<mpsc::Sender<ChatEvent>>,
+) -> Result<LlmApiResponse, ChatHandleError> {
+    let text = request.text.unwrap_or_default();
+    if text.trim().is_empty() && request.attachments.is_empty() {
+        return Err(ChatHandleError::EmptyMessage);
+    }
+
+    let emit = |event: ChatEvent| {
+        let events = events.clone();
+        async move {
+            if let Some(tx) = events {
+                // The client may have gone away; the turn still completes.
+                let _ = tx.send(event).await;
+            }
+        }
+    };
+
+    let cfg = &state.cfg;
+    let turn = AiTurn::resolve(
+        state,
+        RouteKey {
+            provider: "chat",
+            ..Default::default()
+        },
+        &request.user_id,
+        thread_id_for_chat(cfg, &request.user_id),
+    )
+    .await;
+    if turn.is_paused().await {
+        return Err(ChatHandleError::Paused);
+    }
+    if !super::admit(state, &turn.chat, &text) {
+        return Err(ChatHandleError::RateLimited);
+    }
+
+    emit(ChatEvent::Status {
+        status: "queued".to_string(),
+    })
+    .await;
+    let guard = state.mutex_swapper.lock(turn.thread_id.clone()).await;
+
+    emit(ChatEvent::Typing { active: true }).await;
+
+    let now = Utc::now();
+    let mut data = json!({
+        "text": text,
+        "chat_id": request.user_id,
+        "timestamp": now.timestamp(),
+        "current_date": now.to_string(),
+        "source": "chat",
+    });
+    if !request.attachments.is_empty() {
+        data["attachments"] = json!(request.attachments);
+    }
+
+    let req = turn.input_request(cfg, data);
+    let result = match turn.send(cfg, &req).await {
+        Ok(LlmApiResponse {
+            job_id: Some(job_id),
+            ..
+        }) => {
+            // Other turns of the user needn't wait for the job.
+            drop(guard);
+            let (tx, rx) = oneshot::channel();
+            turn.defer_response(
+                state,
+                &job_id,
+                &request.user_id,
+                move |outcome| async move {
+                    let _ = tx.send(outcome);
+                },
+            );
+            rx.await
+                .unwrap_or_else(|_| Err(AiError::JobTimeout(job_id.clone())))
+        }
+        result => result,
+    };
+
+    emit(ChatEvent::Typing { active: false }).await;
+
+    result.map_err(ChatHandleError::Ai)
+}
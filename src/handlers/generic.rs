@@ -0,0 +1,173 @@
+use super::AiTurn;
+use crate::{
+    AppState,
+    config::GenericProviderConfig,
+    models::ai::LlmApiResponse,
+    services::{
+        ai::AiError,
+        generic::{ReplyVars, send_text_message},
+    },
+};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+#[derive(Debug, Error)]
+pub enum GenericHandleError {
+    #[error("ai call failed: {0}")]
+    Ai(AiError),
+    #[error("reply to '{provider}' failed: {error}")]
+    Reply { provider: String, error: String },
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_text(
+    state: &AppState,
+    provider: &GenericProviderConfig,
+    turn: &AiTurn,
+    chat_id: &str,
+    message_id: &str,
+    body: &str,
+    timestamp: i64,
+    ai_response: bool,
+) -> Result<(), GenericHandleError> {
+    let data = json!({
+        "text": body,
+        "chat_id": chat_id,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": provider.name,
+    });
+
+    respond(
+        state,
+        provider,
+        turn,
+        chat_id,
+        message_id,
+        data,
+        ai_response,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_unsupported(
+    state: &AppState,
+    provider: &GenericProviderConfig,
+    turn: &AiTurn,
+    chat_id: &str,
+    message_id: &str,
+    message_type: &str,
+    timestamp: i64,
+    ai_response: bool,
+) -> Result<(), GenericHandleError> {
+    let data = json!({
+        "unsupported_message_type": message_type,
+        "chat_id": chat_id,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": provider.name,
+    });
+
+    respond(
+        state,
+        provider,
+        turn,
+        chat_id,
+        message_id,
+        data,
+        ai_response,
+    )
+    .await
+}
+
+async fn respond(
+    state: &AppState,
+    provider: &GenericProviderConfig,
+    turn: &AiTurn,
+    chat_id: &str,
+    message_id: &str,
+    data: Value,
+    ai_response: bool,
+) -> Result<(), GenericHandleError> {
+    let cfg = &state.cfg;
+    // Chat ids of different providers may collide, so scope the lock by provider.
+    let _guard = state
+        .mutex_swapper
+        .lock(format!("{}:{}", provider.name, chat_id))
+        .await;
+
+    let req = turn.input_request(cfg, data);
+
+    if ai_response {
+        let ai_res: LlmApiResponse = turn
+            .send(&state.cfg, &req)
+            .await
+            .map_err(GenericHandleError::Ai)?;
+        if let Some(job_id) = &ai_res.job_id {
+            let owned_state = state.clone();
+            let provider = provider.clone();
+            let owned_chat_id = chat_id.to_string();
+            let owned_message_id = message_id.to_string();
+            turn.defer(state, job_id, chat_id, move |replies| async move {
+                let Some(template) = provider.reply.as_ref() else {
+                    return;
+                };
+                let _guard = owned_state
+                    .mutex_swapper
+                    .lock(format!("{}:{}", provider.name, owned_chat_id))
+                    .await;
+                for reply in replies {
+                    let vars = ReplyVars {
+                        chat_id: &owned_chat_id,
+                        message_id: &owned_message_id,
+                        text: &reply,
+                    };
+                    if let Err(err) = send_text_message(&owned_state.http, template, vars).await {
+                        warn!(
+                            "Failed to send deferred reply to {} via '{}': {}",
+                            owned_chat_id, provider.name, err
+                        );
+                        return;
+                    }
+                }
+            });
+            return Ok(());
+        }
+
+        for reply in ai_res.replies() {
+            let Some(template) = provider.reply.as_ref() else {
+                debug!(
+                    "Provider '{}' has no reply template, dropping AI reply",
+                    provider.name
+                );
+                return Ok(());
+            };
+
+            send_text_message(
+                &state.http,
+                template,
+                ReplyVars {
+                    chat_id,
+                    message_id,
+                    text: &reply,
+                },
+            )
+            .await
+            .map_err(|error| GenericHandleError::Reply {
+                provider: provider.name.clone(),
+                error,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn current_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or(Utc::now())
+        .to_string()
+}
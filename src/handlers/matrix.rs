@@ -0,0 +1,207 @@
+use super::AiTurn;
+use crate::{
+    AppState,
+    models::ai::LlmApiResponse,
+    services::{ai::AiError, matrix::MatrixClient},
+};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+#[derive(Debug, Error)]
+pub enum MatrixHandleError {
+    #[error("ai call failed: {0}")]
+    Ai(AiError),
+    #[error("matrix api call failed: {0}")]
+    Matrix(String),
+    #[error("matrix client not configured")]
+    NotConfigured,
+}
+
+// Same idea as the WAHA `TypingGuard`: make sure the typing notification is cleared.
+struct TypingGuard {
+    client: MatrixClient,
+    room_id: String,
+    stopped: bool,
+}
+
+impl TypingGuard {
+    async fn stop_now(mut self) {
+        if let Err(e) = self.client.set_typing(&self.room_id, false).await {
+            debug!("Failed to stop Matrix typing notification: {}", e);
+        }
+        self.stopped = true;
+    }
+}
+
+impl Drop for TypingGuard {
+    fn drop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        let client = self.client.clone();
+        let room_id = self.room_id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.set_typing(&room_id, false).await {
+                debug!("Failed to stop Matrix typing notification: {}", e);
+            }
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_text(
+    state: &AppState,
+    turn: &AiTurn,
+    room_id: &str,
+    sender: &str,
+    event_id: &str,
+    body: &str,
+    timestamp: i64,
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), MatrixHandleError> {
+    let data = json!({
+        "text": body,
+        "chat_id": room_id,
+        "sender": sender,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": "matrix",
+    });
+
+    respond(
+        state,
+        turn,
+        room_id,
+        event_id,
+        data,
+        typing,
+        send_seen,
+        ai_response,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_unsupported(
+    state: &AppState,
+    turn: &AiTurn,
+    room_id: &str,
+    sender: &str,
+    event_id: &str,
+    message_type: &str,
+    timestamp: i64,
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), MatrixHandleError> {
+    let data = json!({
+        "unsupported_message_type": message_type,
+        "chat_id": room_id,
+        "sender": sender,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": "matrix",
+    });
+
+    respond(
+        state,
+        turn,
+        room_id,
+        event_id,
+        data,
+        typing,
+        send_seen,
+        ai_response,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn respond(
+    state: &AppState,
+    turn: &AiTurn,
+    room_id: &str,
+    event_id: &str,
+    data: serde_json::Value,
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), MatrixHandleError> {
+    let cfg = &state.cfg;
+    let _guard = state.mutex_swapper.lock(room_id.to_string()).await;
+    let client = state
+        .matrix_client
+        .as_ref()
+        .cloned()
+        .ok_or(MatrixHandleError::NotConfigured)?;
+
+    if send_seen {
+        if let Err(err) = client.send_read_receipt(room_id, event_id).await {
+            debug!("Failed to send Matrix read receipt: {}", err);
+        }
+    }
+
+    let mut typing_guard = if typing {
+        client
+            .set_typing(room_id, true)
+            .await
+            .map_err(MatrixHandleError::Matrix)?;
+        Some(TypingGuard {
+            client: client.clone(),
+            room_id: room_id.to_string(),
+            stopped: false,
+        })
+    } else {
+        None
+    };
+
+    let req = turn.input_request(cfg, data);
+
+    if ai_response {
+        let ai_res: LlmApiResponse = turn
+            .send(&state.cfg, &req)
+            .await
+            .map_err(MatrixHandleError::Ai)?;
+        if let Some(job_id) = &ai_res.job_id {
+            // The reply comes through the AI callback; the typing guard is dropped here.
+            let owned_state = state.clone();
+            let owned_room_id = room_id.to_string();
+            turn.defer(state, job_id, room_id, move |replies| async move {
+                let _guard = owned_state.mutex_swapper.lock(owned_room_id.clone()).await;
+                for reply in replies {
+                    if let Err(err) = client.send_text_message(&owned_room_id, &reply).await {
+                        warn!(
+                            "Failed to send deferred reply to {}: {}",
+                            owned_room_id, err
+                        );
+                        return;
+                    }
+                }
+            });
+            return Ok(());
+        }
+
+        for reply in ai_res.replies() {
+            if let Some(guard) = typing_guard.take() {
+                guard.stop_now().await;
+            }
+
+            client
+                .send_text_message(room_id, &reply)
+                .await
+                .map_err(MatrixHandleError::Matrix)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn current_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or(Utc::now())
+        .to_string()
+}
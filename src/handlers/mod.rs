@@ -1,19 +1,50 @@
 use crate::{
     AppState,
+    config::{AgentKnobs, Config, GenericProviderConfig},
     models::{
+        ai::{InputRequest, LlmApiResponse},
         common::IncomingMessage,
-        wacraft::{WacraftInteractive, WacraftReceiverData, WacraftWebhook},
+        consent::ConsentStatus,
+        handoff::LoopAlert,
+        matrix::MatrixRoomEvent,
+        messages::{OutboundContent, OutboundMessageRequest, ScheduledMessage},
+        signal::SignalEnvelope,
+        wacraft::{WacraftInteractive, WacraftReceiverData, WacraftSenderData, WacraftWebhook},
         waha::WahaWebhook,
+        webchat::WebChatFrame,
+    },
+    services::{
+        ai::{AiBackend, AiError, router::RouteKey, stream::ChunkSplitter},
+        flood::Admission,
+        generic::field_pointer,
+        handoff::{ChatRef, Handoff},
+        loops::LoopDetector,
+    },
+    store::{blocking, threads::current_thread_id},
+    utils::{
+        thread_id_for_generic, thread_id_for_matrix, thread_id_for_signal, thread_id_for_wacraft,
+        thread_id_for_waha,
     },
-    utils::{thread_id_for_wacraft, thread_id_for_waha},
 };
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
 use chrono::Utc;
+use scheduled::ScheduleTarget;
+use serde_json::{Value, json};
+use std::sync::Arc;
 use text::TextHandleError;
 use thiserror::Error;
+use tokio::sync::mpsc;
 use tracing::{debug, warn};
 
+pub mod chat;
+pub mod generic;
+pub mod matrix;
+pub mod outbound;
+pub mod scheduled;
+pub mod signal;
 pub mod text;
 pub mod wacraft;
+pub mod webchat;
 
 #[derive(Debug, Error)]
 pub enum HandleError {
@@ -21,6 +52,12 @@ pub enum HandleError {
     Text(#[from] TextHandleError),
     #[error(transparent)]
     Wacraft(#[from] wacraft::WacraftHandleError),
+    #[error(transparent)]
+    Generic(#[from] generic::GenericHandleError),
+    #[error(transparent)]
+    Matrix(#[from] matrix::MatrixHandleError),
+    #[error(transparent)]
+    Signal(#[from] signal::SignalHandleError),
     #[error("Event '{0}' not supported")]
     EventNotSupported(String),
     #[error("Payload is missing")]
@@ -29,6 +66,493 @@ pub enum HandleError {
     MissingField(&'static str),
 }
 
+/// The AI side of a conversation: its thread id plus the backend and knobs picked by the
+/// routing table (`AI_ROUTES_PATH`), or the global defaults when no route matches.
+pub struct AiTurn {
+    pub thread_id: String,
+    pub backend: Arc<dyn AiBackend>,
+    pub knobs: AgentKnobs,
+    /// Where the agent's `schedule` entries are sent, when the scheduler is enabled and
+    /// the provider can send proactive messages
+    pub schedule_target: Option<ScheduleTarget>,
+    /// The conversation, for handoff pauses
+    pub chat: ChatRef,
+    /// The contact's consent, passed to the agent as `data.consent` (`CONSENT_ENABLED`)
+    pub consent: Option<ConsentStatus>,
+    /// The chat's pending and failed scheduled messages, passed to the agent as
+    /// `data.scheduled` so it knows the ids of the entries it created
+    pub scheduled: Vec<ScheduledMessage>,
+    handoff: Arc<Handoff>,
+    loops: Arc<LoopDetector>,
+}
+
+impl AiTurn {
+    /// Resolves the route for `key`. A route's `thread_prefix` replaces the provider prefix
+    /// baked into `default_thread_id`.
+    pub async fn resolve(
+        state: &AppState,
+        key: RouteKey<'_>,
+        chat_id: &str,
+        default_thread_id: String,
+    ) -> Self {
+        let route = state.ai.resolve(&key);
+        if let Some(name) = &route.name {
+            debug!("Conversation {} routed to '{}'", chat_id, name);
+        }
+        let thread_id = match &route.thread_prefix {
+            Some(prefix) => format!("{prefix}{chat_id}"),
+            None => default_thread_id,
+        };
+        // Threads reset through `/admin/threads/reset` continue under a new id.
+        let threads = Arc::clone(&state.threads);
+        let base_id = thread_id.clone();
+        let thread_id = match blocking(move || threads.generation(&base_id)).await {
+            Ok(generation) => current_thread_id(&thread_id, generation),
+            Err(err) => {
+                warn!("Failed to read the generation of {}: {}", thread_id, err);
+                thread_id
+            }
+        };
+        let provider = match key.provider {
+            "waha" => Some("waha"),
+            "wacraft" => Some("wacraft"),
+            "matrix" => Some("matrix"),
+            "signal" => Some("signal"),
+            _ => None,
+        };
+        let schedule_target = state
+            .scheduler
+            .as_ref()
+            .zip(provider)
+            .map(|(store, provider)| ScheduleTarget {
+                store: Arc::clone(store),
+                provider,
+                session: key.session.map(str::to_string),
+                to: chat_id.to_string(),
+            });
+        let scheduled = match &schedule_target {
+            Some(target) => {
+                let target = target.clone();
+                blocking(move || target.store.list(target.provider, &target.to))
+                    .await
+                    .unwrap_or_else(|err| {
+                        warn!("Failed to list scheduled messages of {}: {}", chat_id, err);
+                        Vec::new()
+                    })
+            }
+            None => Vec::new(),
+        };
+        let consent = if state.consent.is_enabled() {
+            state
+                .consent
+                .status(key.provider, chat_id)
+                .await
+                .map_err(|err| warn!("Failed to read consent of {}: {}", chat_id, err))
+                .ok()
+        } else {
+            None
+        };
+        Self {
+            thread_id,
+            backend: route.backend,
+            knobs: route.knobs,
+            schedule_target,
+            chat: ChatRef {
+                provider: key.provider.to_string(),
+                session: key.session.or(key.messaging_product_id).map(str::to_string),
+                chat_id: chat_id.to_string(),
+            },
+            consent,
+            scheduled,
+            handoff: Arc::clone(&state.handoff),
+            loops: Arc::clone(&state.loops),
+        }
+    }
+
+    /// Whether a human took over this chat. Dispatchers skip the turn entirely then.
+    pub async fn is_paused(&self) -> bool {
+        let paused = self.handoff.is_paused(&self.chat).await;
+        if paused {
+            debug!(
+                "AI replies paused for {}, leaving the message to the operator",
+                self.chat.chat_id
+            );
+        }
+        paused
+    }
+
+    /// Applies what the reply asks for besides its text: `schedule` entries and `handoff`.
+    fn apply_actions(&self, res: &LlmApiResponse) {
+        apply_actions(
+            &self.chat,
+            &self.handoff,
+            self.schedule_target.as_ref(),
+            &self.thread_id,
+            res,
+        );
+    }
+
+    /// Builds the AI request for this turn, taking knobs from the route, then `Config`, and
+    /// adds the contact's `consent` and `scheduled` messages to `data`.
+    pub fn input_request(&self, cfg: &Config, mut data: Value) -> InputRequest {
+        if let Some(data) = data.as_object_mut() {
+            if let Some(consent) = self.consent {
+                data.insert("consent".to_string(), json!(consent.as_str()));
+            }
+            if !self.scheduled.is_empty() {
+                let scheduled: Vec<Value> = self
+                    .scheduled
+                    .iter()
+                    .map(|message| {
+                        json!({
+                            "id": message.id,
+                            "send_at": message.send_at,
+                            "status": message.status,
+                        })
+                    })
+                    .collect();
+                data.insert("scheduled".to_string(), Value::Array(scheduled));
+            }
+        }
+        let knobs = &self.knobs;
+        InputRequest {
+            data,
+            chat_interface: knobs
+                .chat_interface
+                .clone()
+                .unwrap_or_else(|| cfg.chat_interface.clone()),
+            max_retries: knobs.max_retries.unwrap_or(cfg.max_retries),
+            loop_threshold: knobs.loop_threshold.unwrap_or(cfg.loop_threshold),
+            top_k: knobs.top_k.unwrap_or(cfg.top_k),
+            summarize_message_window: knobs
+                .summarize_message_window
+                .unwrap_or(cfg.summarize_message_window),
+            summarize_message_keep: knobs
+                .summarize_message_keep
+                .unwrap_or(cfg.summarize_message_keep),
+            summarize_system_messages: knobs
+                .summarize_system_messages
+                .unwrap_or(cfg.summarize_system_messages),
+            thread_id: self.thread_id.clone(),
+            history: None,
+        }
+    }
+
+    /// Asks the backend for a reply. When the call fails (after the retries and circuit
+    /// breaker in front of every backend) and `AI_FALLBACK_MESSAGE` is set, that message is
+    /// answered instead of dropping the turn.
+    pub async fn send(&self, cfg: &Config, req: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        match self.backend.send_user_message(req).await {
+            Ok(res) => {
+                // A deferred turn counts as answered once its callback is delivered.
+                if res.job_id.is_none() {
+                    self.loops.on_reply(&self.chat);
+                }
+                self.apply_actions(&res);
+                Ok(res)
+            }
+            Err(err) => fallback_reply(cfg, &self.thread_id, err),
+        }
+    }
+
+    /// Streams the reply, handing every finished chunk but the last to `deliver` as soon
+    /// as it is complete. Returns what is still to be sent: the last chunk, or the whole
+    /// reply when nothing was streamed. Callers send those like a single-shot reply, so the
+    /// typing indicator can be cleared before the final message.
+    /// A turn the agent accepted as a background job comes back as `TurnReply::Deferred`.
+    pub async fn stream<E, F, Fut>(
+        &self,
+        cfg: &Config,
+        req: &InputRequest,
+        mut deliver: F,
+    ) -> Result<TurnReply, E>
+    where
+        E: From<AiError>,
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+    {
+        let (tx, mut rx) = mpsc::channel::<String>(32);
+        let chunks = async {
+            let mut splitter = ChunkSplitter::new(cfg.ai_stream_min_chunk_chars);
+            let mut held: Option<String> = None;
+            let mut delivered = 0;
+            while let Some(delta) = rx.recv().await {
+                for chunk in splitter.push(&delta) {
+                    if let Some(previous) = held.replace(chunk) {
+                        deliver(previous).await?;
+                        delivered += 1;
+                    }
+                }
+            }
+            let tail: Vec<String> = held.into_iter().chain(splitter.finish()).collect();
+            Ok::<_, E>((delivered, tail))
+        };
+        let (result, chunks) = tokio::join!(self.backend.stream_user_message(req, tx), chunks);
+        let (delivered, mut tail) = chunks?;
+        if let Ok(res) = &result {
+            if res.job_id.is_none() {
+                self.loops.on_reply(&self.chat);
+            }
+            self.apply_actions(res);
+        }
+
+        match result {
+            Ok(res) if delivered == 0 && tail.is_empty() => Ok(match res.job_id {
+                Some(job_id) => TurnReply::Deferred(job_id),
+                None => TurnReply::Replies(res.replies()),
+            }),
+            Ok(_) => Ok(TurnReply::Replies(tail)),
+            Err(err) => {
+                tail.extend(fallback_reply(cfg, &self.thread_id, err)?.replies());
+                Ok(TurnReply::Replies(tail))
+            }
+        }
+    }
+
+    /// Parks a turn the agent accepted as a background job (`202 {job_id}`). `deliver` gets
+    /// the replies once `POST /v1/callbacks/ai/{job_id}` arrives, or the fallback message
+    /// (possibly nothing) when the job times out; it runs without the chat lock held.
+    pub fn defer<F, Fut>(&self, state: &AppState, job_id: &str, chat_id: &str, deliver: F)
+    where
+        F: FnOnce(Vec<String>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let thread_id = self.thread_id.clone();
+        self.defer_response(state, job_id, chat_id, move |outcome| async move {
+            let replies = match outcome {
+                Ok(res) => res.replies(),
+                Err(err) => {
+                    warn!("Deferred AI turn for {} failed: {}", thread_id, err);
+                    Vec::new()
+                }
+            };
+            deliver(replies).await;
+        });
+    }
+
+    /// Like `defer`, but `deliver` gets the whole response, or the error when the job timed
+    /// out and no fallback message is set. The callback's response goes through the
+    /// backend's wrappers (`AiBackend::finish_deferred`) like any other reply.
+    pub fn defer_response<F, Fut>(&self, state: &AppState, job_id: &str, chat_id: &str, deliver: F)
+    where
+        F: FnOnce(Result<LlmApiResponse, AiError>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let cfg = state.cfg.clone();
+        let thread_id = self.thread_id.clone();
+        let backend = Arc::clone(&self.backend);
+        let schedule_target = self.schedule_target.clone();
+        let chat = self.chat.clone();
+        let handoff = Arc::clone(&self.handoff);
+        let loops = Arc::clone(&self.loops);
+        state.ai_jobs.defer(
+            job_id,
+            chat_id,
+            Box::new(move |outcome| {
+                Box::pin(async move {
+                    let outcome = match outcome {
+                        Ok(mut res) => {
+                            backend.finish_deferred(&thread_id, &mut res).await;
+                            Ok(res)
+                        }
+                        Err(err) => fallback_reply(&cfg, &thread_id, err),
+                    };
+                    if let Ok(res) = &outcome {
+                        loops.on_reply(&chat);
+                        apply_actions(&chat, &handoff, schedule_target.as_ref(), &thread_id, res);
+                    }
+                    deliver(outcome).await;
+                })
+            }),
+        );
+    }
+}
+
+/// What is left to send after `AiTurn::stream`.
+pub enum TurnReply {
+    /// Messages to send now
+    Replies(Vec<String>),
+    /// The agent took the turn as a background job; hand it to `AiTurn::defer`
+    Deferred(String),
+}
+
+fn apply_actions(
+    chat: &ChatRef,
+    handoff: &Handoff,
+    schedule_target: Option<&ScheduleTarget>,
+    thread_id: &str,
+    res: &LlmApiResponse,
+) {
+    if !res.schedule.is_empty() {
+        match schedule_target {
+            Some(target) => scheduled::schedule_replies(target, &res.schedule),
+            None => warn!(
+                "Dropping {} scheduled replies for {}: scheduler disabled or provider can't send proactively",
+                res.schedule.len(),
+                thread_id
+            ),
+        }
+    }
+    if res.handoff {
+        match chat.provider.as_str() {
+            // No operator can answer there.
+            "chat" | "webchat" => warn!("Ignoring handoff for {}", thread_id),
+            _ => handoff.pause(chat, "agent"),
+        }
+    }
+}
+
+/// When `AI_FALLBACK_MESSAGE` is set, a failed AI call still gets an answer.
+fn fallback_reply(cfg: &Config, thread_id: &str, err: AiError) -> Result<LlmApiResponse, AiError> {
+    let Some(message) = &cfg.ai_resilience.fallback_message else {
+        return Err(err);
+    };
+    warn!(
+        "AI call failed for {}, sending fallback reply: {}",
+        thread_id, err
+    );
+    Ok(LlmApiResponse {
+        next_step: "fallback".to_string(),
+        next_step_reason: err.to_string(),
+        response: Some(message.clone()),
+        parts: Vec::new(),
+        job_id: None,
+        schedule: Vec::new(),
+        handoff: false,
+    })
+}
+
+/// Opt-out and opt-in keywords (`CONSENT_*`) never reach the AI: the contact's consent is
+/// recorded and the confirmation goes out in the background, if `admit` lets the message
+/// through. Returns `true` when the message was such a keyword.
+async fn take_consent_keyword(state: &AppState, chat: &ChatRef, body: &str) -> bool {
+    let Some(status) = state.consent.keyword(body) else {
+        return false;
+    };
+    if let Err(err) = state
+        .consent
+        .record(&chat.provider, &chat.chat_id, status, body)
+        .await
+    {
+        warn!("Failed to record consent of {}: {}", chat.chat_id, err);
+        return true;
+    }
+    // Keywords count against the flood limits and loop detection like any message, so
+    // they can't be used to make the bot answer without end.
+    if !admit(state, chat, body) {
+        return true;
+    }
+    if let Some(text) = state.consent.reply(status) {
+        let request = OutboundMessageRequest {
+            provider: chat.provider.clone(),
+            session: chat.session.clone(),
+            to: chat.chat_id.clone(),
+            content: OutboundContent::Text {
+                text: text.to_string(),
+            },
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            match outbound::answer(&state, request).await {
+                Ok(()) => {}
+                // Config-defined providers can't send on their own.
+                Err(outbound::OutboundError::UnknownProvider(_)) => {}
+                Err(err) => warn!("Failed to send the consent confirmation: {}", err),
+            }
+        });
+    }
+    true
+}
+
+/// Flood protection (`INBOUND_RATE_*`) and bot-loop detection (`BOT_LOOP_*`), checked
+/// before the chat lock is taken so a flood never queues up behind it. The slow-down reply
+/// goes out in the background; the chat API reports it in its `429` instead.
+fn admit(state: &AppState, chat: &ChatRef, body: &str) -> bool {
+    match state.flood.check(chat) {
+        Admission::Accept => {}
+        Admission::Drop => return false,
+        Admission::SlowDown => {
+            let text = state.flood.slow_down_message().unwrap_or_default();
+            match chat.provider.as_str() {
+                "chat" => return false,
+                "webchat" => {
+                    state.webchat.send(
+                        &chat.chat_id,
+                        WebChatFrame::Reply {
+                            text: text.to_string(),
+                        },
+                    );
+                    return false;
+                }
+                _ => {}
+            }
+            let request = OutboundMessageRequest {
+                provider: chat.provider.clone(),
+                session: chat.session.clone(),
+                to: chat.chat_id.clone(),
+                content: OutboundContent::Text {
+                    text: text.to_string(),
+                },
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                match outbound::answer(&state, request).await {
+                    Ok(()) => {}
+                    // Config-defined providers can't send on their own; they just drop.
+                    Err(outbound::OutboundError::UnknownProvider(_)) => {}
+                    Err(err) => warn!("Failed to send the slow-down reply: {}", err),
+                }
+            });
+            return false;
+        }
+    }
+    match state.loops.on_inbound(chat, body) {
+        Some(reason) => {
+            pause_loop(state, chat, reason);
+            false
+        }
+        None => true,
+    }
+}
+
+/// Pauses a chat that looks like a bot loop and posts a `LoopAlert` to `BOT_LOOP_ALERT_URL`.
+fn pause_loop(state: &AppState, chat: &ChatRef, reason: String) {
+    warn!(
+        "Bot loop suspected in {} ({}), pausing AI replies",
+        chat.chat_id, reason
+    );
+    let pause = match state
+        .handoff
+        .pause_for(chat, "loop", state.cfg.bot_loops.pause_secs)
+    {
+        Ok(pause) => pause,
+        Err(err) => {
+            warn!("Failed to pause {}: {}", chat.chat_id, err);
+            return;
+        }
+    };
+    let Some(url) = state.cfg.bot_loops.alert_url.clone() else {
+        return;
+    };
+    let alert = LoopAlert {
+        event: "bot_loop",
+        reason,
+        pause,
+    };
+    let http = state.http.clone();
+    tokio::spawn(async move {
+        let result = http
+            .post(url)
+            .json(&alert)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+        if let Err(err) = result {
+            warn!("Failed to post the loop alert: {}", err);
+        }
+    });
+}
+
 pub async fn dispatch_waha(
     webhook: WahaWebhook,
     state: AppState,
@@ -45,6 +569,28 @@ pub async fn dispatch_waha(
     let payload = webhook.payload.ok_or(HandleError::MissingPayload)?;
 
     if payload.from_me {
+        // Sent from the bot's own number: our echo, or a human typing on the phone.
+        let chat = ChatRef {
+            provider: "waha".to_string(),
+            session: Some(webhook.session.clone()),
+            chat_id: payload.to.clone(),
+        };
+        let body = payload.body.as_deref().unwrap_or_default();
+        if state.handoff.on_own_message(&chat, body) {
+            let turn = AiTurn::resolve(
+                &state,
+                RouteKey {
+                    provider: "waha",
+                    session: Some(&webhook.session),
+                    to: Some(&payload.from),
+                    ..Default::default()
+                },
+                &chat.chat_id,
+                thread_id_for_waha(&state.cfg, &chat.chat_id),
+            )
+            .await;
+            sync_operator_message(&state, &turn, "waha", body).await;
+        }
         return Ok(());
     }
 
@@ -63,18 +609,39 @@ pub async fn dispatch_waha(
     }
 
     let chat_id = payload.from;
-    if let Some(wa_ids) = allowed_wa_ids {
-        if !wa_ids.contains(&chat_id) {
-            warn!(
-                "DEV MODE: Blocking message from '{}' as it's not in the allowed list: {:?}",
-                chat_id, wa_ids
-            );
-            return Ok(());
-        }
+    if !state
+        .access
+        .is_allowed(
+            "waha",
+            Some(&webhook.session),
+            &chat_id,
+            allowed_wa_ids.as_deref(),
+        )
+        .await
+    {
+        return Ok(());
     }
 
     let session = webhook.session;
-    let thread_id = thread_id_for_waha(&state.cfg, &chat_id);
+    let turn = AiTurn::resolve(
+        &state,
+        RouteKey {
+            provider: "waha",
+            session: Some(&session),
+            to: Some(&payload.to),
+            ..Default::default()
+        },
+        &chat_id,
+        thread_id_for_waha(&state.cfg, &chat_id),
+    )
+    .await;
+    let body = payload_body.as_deref().unwrap_or_default();
+    if take_consent_keyword(&state, &turn.chat, body).await
+        || turn.is_paused().await
+        || !admit(&state, &turn.chat, body)
+    {
+        return Ok(());
+    }
     let timestamp = payload.timestamp;
     let message_id = payload.id;
 
@@ -103,7 +670,7 @@ pub async fn dispatch_waha(
             text::handle_text(
                 &state,
                 &session,
-                &thread_id,
+                &turn,
                 &chat_id,
                 &message_id,
                 &body,
@@ -123,7 +690,7 @@ pub async fn dispatch_waha(
             text::handle_unsupported(
                 &state,
                 &session,
-                &thread_id,
+                &turn,
                 &chat_id,
                 &message_id,
                 &r#type,
@@ -147,6 +714,9 @@ pub async fn dispatch_wacraft(
     ai_response: bool,
 ) -> Result<(), HandleError> {
     let Some(receiver) = webhook.receiver_data else {
+        if let Some(sender) = &webhook.sender_data {
+            return dispatch_wacraft_outbound(&webhook, sender, &state).await;
+        }
         debug!("Wacraft webhook without receiver_data, ignoring");
         return Ok(());
     };
@@ -156,14 +726,17 @@ pub async fn dispatch_wacraft(
         .clone()
         .ok_or(HandleError::MissingField("receiver_data.from"))?;
 
-    if let Some(ids) = allowed_wa_ids.as_ref() {
-        if !ids.contains(&chat_id) {
-            warn!(
-                "DEV MODE: Blocking message from '{}' as it's not in the allowed list: {:?}",
-                chat_id, ids
-            );
-            return Ok(());
-        }
+    if !state
+        .access
+        .is_allowed(
+            "wacraft",
+            webhook.messaging_product_id.as_deref(),
+            &chat_id,
+            allowed_wa_ids.as_deref(),
+        )
+        .await
+    {
+        return Ok(());
     }
 
     let message_id = receiver.id.clone().unwrap_or_else(|| webhook.id.clone());
@@ -180,15 +753,33 @@ pub async fn dispatch_wacraft(
         .and_then(|ts| ts.parse::<i64>().ok())
         .unwrap_or_else(|| Utc::now().timestamp());
 
-    let thread_id = thread_id_for_wacraft(&state.cfg, &chat_id);
+    let turn = AiTurn::resolve(
+        &state,
+        RouteKey {
+            provider: "wacraft",
+            messaging_product_id: webhook.messaging_product_id.as_deref(),
+            to: receiver.extra.get("to").and_then(Value::as_str),
+            ..Default::default()
+        },
+        &chat_id,
+        thread_id_for_wacraft(&state.cfg, &chat_id),
+    )
+    .await;
+    let message = normalize_wacraft_message(&receiver);
+    if take_consent_keyword(&state, &turn.chat, message.body()).await
+        || turn.is_paused().await
+        || !admit(&state, &turn.chat, message.body())
+    {
+        return Ok(());
+    }
 
-    match normalize_wacraft_message(&receiver) {
+    match message {
         NormalizedMessage::Skip => Ok(()),
         NormalizedMessage::Text(body) => {
             wacraft::handle_text(
                 &state,
                 &session,
-                &thread_id,
+                &turn,
                 &chat_id,
                 &message_id,
                 &body,
@@ -204,7 +795,7 @@ pub async fn dispatch_wacraft(
             wacraft::handle_unsupported(
                 &state,
                 &session,
-                &thread_id,
+                &turn,
                 &chat_id,
                 &message_id,
                 &kind,
@@ -219,12 +810,390 @@ pub async fn dispatch_wacraft(
     }
 }
 
+/// A message sent from the business number: our echo, or an operator answering in Wacraft.
+async fn dispatch_wacraft_outbound(
+    webhook: &WacraftWebhook,
+    sender: &WacraftSenderData,
+    state: &AppState,
+) -> Result<(), HandleError> {
+    let chat_id = sender
+        .to
+        .clone()
+        .ok_or(HandleError::MissingField("sender_data.to"))?;
+    let body = sender
+        .text
+        .as_ref()
+        .and_then(|text| text.body.as_deref())
+        .unwrap_or_default();
+    let chat = ChatRef {
+        provider: "wacraft".to_string(),
+        session: webhook.messaging_product_id.clone(),
+        chat_id: chat_id.clone(),
+    };
+    if state.handoff.on_own_message(&chat, body) {
+        let turn = AiTurn::resolve(
+            state,
+            RouteKey {
+                provider: "wacraft",
+                messaging_product_id: webhook.messaging_product_id.as_deref(),
+                // The business number sent this one, so it is the route's receiving number.
+                to: sender.extra.get("from").and_then(Value::as_str),
+                ..Default::default()
+            },
+            &chat_id,
+            thread_id_for_wacraft(&state.cfg, &chat_id),
+        )
+        .await;
+        sync_operator_message(state, &turn, "wacraft", body).await;
+    }
+    Ok(())
+}
+
+/// Forwards what a human operator wrote in the chat to the agent, as context on the chat's
+/// thread, marked with `HANDOFF_OPERATOR_CHAT_INTERFACE` so it isn't answered.
+async fn sync_operator_message(state: &AppState, turn: &AiTurn, source: &str, body: &str) {
+    let chat = &turn.chat;
+    // Blocked chats never reach the agent, not even as context.
+    if !state
+        .access
+        .is_allowed(&chat.provider, chat.session.as_deref(), &chat.chat_id, None)
+        .await
+    {
+        return;
+    }
+    let chat_id = &chat.chat_id;
+    let _guard = state.mutex_swapper.lock(chat_id.clone()).await;
+    let mut req = turn.input_request(
+        &state.cfg,
+        json!({
+            "text": body,
+            "role": "operator",
+            "chat_id": chat_id,
+            "source": source,
+            "current_date": Utc::now().to_string(),
+        }),
+    );
+    req.chat_interface = state.cfg.handoff.operator_chat_interface.clone();
+    match turn.backend.add_context(&req).await {
+        Ok(()) => debug!("Synced operator message to {}", turn.thread_id),
+        Err(err) => warn!(
+            "Failed to sync operator message to {}: {}",
+            turn.thread_id, err
+        ),
+    }
+}
+
+pub async fn dispatch_matrix(
+    room_id: &str,
+    event: MatrixRoomEvent,
+    state: AppState,
+    typing: bool,
+    send_seen: bool,
+) -> Result<(), HandleError> {
+    if event.event_type != "m.room.message" {
+        return Err(HandleError::EventNotSupported(event.event_type));
+    }
+
+    if !state.access.is_allowed("matrix", None, room_id, None).await {
+        return Ok(());
+    }
+
+    let turn = AiTurn::resolve(
+        &state,
+        RouteKey {
+            provider: "matrix",
+            ..Default::default()
+        },
+        room_id,
+        thread_id_for_matrix(&state.cfg, room_id),
+    )
+    .await;
+    let body = event.content.body.as_deref().unwrap_or_default();
+    if take_consent_keyword(&state, &turn.chat, body).await
+        || turn.is_paused().await
+        || !admit(&state, &turn.chat, body)
+    {
+        return Ok(());
+    }
+    let timestamp = event.origin_server_ts / 1000;
+    let msgtype = event.content.msgtype.as_deref().unwrap_or("unknown");
+
+    match msgtype {
+        // Notices are the conventional msgtype for bot output; never answer them.
+        "m.notice" => Ok(()),
+        "m.text" | "m.emote" => {
+            let body = event.content.body.clone().unwrap_or_default();
+            if body.trim().is_empty() {
+                debug!("Skipping empty Matrix message {}", event.event_id);
+                return Ok(());
+            }
+            matrix::handle_text(
+                &state,
+                &turn,
+                room_id,
+                &event.sender,
+                &event.event_id,
+                &body,
+                timestamp,
+                typing,
+                send_seen,
+                true,
+            )
+            .await?;
+            Ok(())
+        }
+        other => {
+            matrix::handle_unsupported(
+                &state,
+                &turn,
+                room_id,
+                &event.sender,
+                &event.event_id,
+                other,
+                timestamp,
+                typing,
+                send_seen,
+                true,
+            )
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+pub async fn dispatch_signal(
+    envelope: SignalEnvelope,
+    state: AppState,
+    typing: bool,
+    send_seen: bool,
+) -> Result<(), HandleError> {
+    // Receipts, typing and sync messages carry no data message; nothing to answer.
+    let Some(message) = envelope.data_message else {
+        return Ok(());
+    };
+
+    let sender = envelope
+        .source_number
+        .or(envelope.source_uuid)
+        .or(envelope.source)
+        .ok_or(HandleError::MissingField("envelope.source"))?;
+
+    // signal-cli-rest-api addresses groups as `group.` + base64(internal group id).
+    let chat_id = match message.group_info.as_ref() {
+        Some(group) => format!("group.{}", BASE64.encode(group.group_id.as_bytes())),
+        None => sender.clone(),
+    };
+
+    if !state
+        .access
+        .is_allowed("signal", None, &chat_id, None)
+        .await
+    {
+        return Ok(());
+    }
+
+    let turn = AiTurn::resolve(
+        &state,
+        RouteKey {
+            provider: "signal",
+            ..Default::default()
+        },
+        &chat_id,
+        thread_id_for_signal(&state.cfg, &chat_id),
+    )
+    .await;
+    let body = message.message.as_deref().unwrap_or_default();
+    if take_consent_keyword(&state, &turn.chat, body).await
+        || turn.is_paused().await
+        || !admit(&state, &turn.chat, body)
+    {
+        return Ok(());
+    }
+    let inbound = signal::SignalInbound {
+        turn: &turn,
+        chat_id: &chat_id,
+        sender: &sender,
+        sent_at: message.timestamp,
+    };
+
+    let body = message.message.unwrap_or_default();
+    if !body.trim().is_empty() {
+        signal::handle_text(
+            &state,
+            inbound,
+            &body,
+            &message.attachments,
+            typing,
+            send_seen,
+            true,
+        )
+        .await?;
+    } else if !message.attachments.is_empty() {
+        signal::handle_attachments(
+            &state,
+            inbound,
+            &message.attachments,
+            typing,
+            send_seen,
+            true,
+        )
+        .await?;
+    } else {
+        debug!("Skipping empty Signal message from {}", sender);
+    }
+
+    Ok(())
+}
+
+pub async fn dispatch_generic(
+    payload: Value,
+    provider: &GenericProviderConfig,
+    state: AppState,
+    allowed_wa_ids: Option<Vec<String>>,
+    ai_response: bool,
+) -> Result<(), HandleError> {
+    let fields = &provider.fields;
+
+    if let Some(expr) = &fields.from_me {
+        if lookup_field(&payload, expr).is_some_and(value_is_truthy) {
+            return Ok(());
+        }
+    }
+
+    let chat_id = lookup_field(&payload, &fields.chat_id)
+        .and_then(value_to_string)
+        .ok_or(HandleError::MissingField("fields.chat_id"))?;
+
+    if !state
+        .access
+        .is_allowed(&provider.name, None, &chat_id, allowed_wa_ids.as_deref())
+        .await
+    {
+        return Ok(());
+    }
+
+    let message_id = fields
+        .message_id
+        .as_ref()
+        .and_then(|expr| lookup_field(&payload, expr))
+        .and_then(value_to_string)
+        .unwrap_or_default();
+
+    let timestamp = fields
+        .timestamp
+        .as_ref()
+        .and_then(|expr| lookup_field(&payload, expr))
+        .and_then(|value| value.as_i64().or_else(|| value.as_str()?.parse().ok()))
+        .unwrap_or_else(|| Utc::now().timestamp());
+
+    let text = fields
+        .text
+        .as_ref()
+        .and_then(|expr| lookup_field(&payload, expr))
+        .and_then(value_to_string);
+
+    let message_type = match &fields.message_type {
+        Some(expr) => lookup_field(&payload, expr)
+            .and_then(value_to_string)
+            .unwrap_or_else(|| "unknown".to_string()),
+        None if text.is_some() => "text".to_string(),
+        None => "unknown".to_string(),
+    };
+
+    let turn = AiTurn::resolve(
+        &state,
+        RouteKey {
+            provider: &provider.name,
+            ..Default::default()
+        },
+        &chat_id,
+        thread_id_for_generic(provider, &chat_id),
+    )
+    .await;
+    let body = text.as_deref().unwrap_or_default();
+    if take_consent_keyword(&state, &turn.chat, body).await
+        || turn.is_paused().await
+        || !admit(&state, &turn.chat, body)
+    {
+        return Ok(());
+    }
+
+    match text {
+        Some(body)
+            if provider.text_types.contains(&message_type) || fields.message_type.is_none() =>
+        {
+            if body.trim().is_empty() {
+                debug!("Skipping empty text body from {}", chat_id);
+                return Ok(());
+            }
+            generic::handle_text(
+                &state,
+                provider,
+                &turn,
+                &chat_id,
+                &message_id,
+                &body,
+                timestamp,
+                ai_response,
+            )
+            .await?;
+        }
+        _ => {
+            generic::handle_unsupported(
+                &state,
+                provider,
+                &turn,
+                &chat_id,
+                &message_id,
+                &message_type,
+                timestamp,
+                ai_response,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves a JSON pointer (`/a/b/0`) or a simple JSONPath (`$.a.b[0]`, `$['a']`).
+fn lookup_field<'a>(payload: &'a Value, expr: &str) -> Option<&'a Value> {
+    payload.pointer(&field_pointer(expr)?)
+}
+
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn value_is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::String(s) => matches!(s.to_lowercase().as_str(), "true" | "1" | "yes"),
+        Value::Number(n) => n.as_i64().is_some_and(|n| n != 0),
+        _ => false,
+    }
+}
+
 enum NormalizedMessage {
     Text(String),
     Unsupported(String),
     Skip,
 }
 
+impl NormalizedMessage {
+    fn body(&self) -> &str {
+        match self {
+            NormalizedMessage::Text(body) => body,
+            _ => "",
+        }
+    }
+}
+
 fn normalize_wacraft_message(data: &WacraftReceiverData) -> NormalizedMessage {
     let message_type = data.message_type.as_deref().unwrap_or("unknown");
 
@@ -0,0 +1,239 @@
+use super::{text::send_reply, wacraft};
+use crate::{
+    AppState,
+    models::{
+        messages::{OutboundContent, OutboundMessageRequest},
+        waha::{WahaFile, WahaFileOut},
+    },
+    services::waha::send_file_message,
+};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum OutboundError {
+    #[error("unknown provider '{0}'")]
+    UnknownProvider(String),
+    #[error("provider '{0}' is not configured")]
+    ProviderNotConfigured(&'static str),
+    #[error("{0} messages are not supported by {1}")]
+    UnsupportedContent(&'static str, &'static str),
+    #[error("invalid message: {0}")]
+    Invalid(&'static str),
+    #[error("rate limit reached for this recipient, retry in {}s", (.0.as_secs_f64().ceil() as u64).max(1))]
+    RateLimited(Duration),
+    #[error("{0} opted out of messages")]
+    OptedOut(String),
+    #[error("send failed: {0}")]
+    Send(String),
+}
+
+/// Sends a message nobody asked for (reminders, notifications, agent follow-ups). Takes
+/// the recipient's chat lock, so it never interleaves with a reply being sent to the same
+/// chat, and spends one token of the recipient's `MESSAGES_RATE_*` budget. Contacts that
+/// opted out (`CONSENT_ENABLED`) are refused. Returns the delivery id.
+pub async fn send(
+    state: &AppState,
+    request: OutboundMessageRequest,
+) -> Result<String, OutboundError> {
+    let provider = validate(state, &request.provider, &request.to, &request.content)?;
+    let to = request.to.trim().to_string();
+    let kind = request.content.kind();
+
+    let opted_out = state
+        .consent
+        .is_opted_out(provider, &to)
+        .await
+        .map_err(|err| OutboundError::Send(format!("consent lookup failed: {err}")))?;
+    if opted_out {
+        return Err(OutboundError::OptedOut(to));
+    }
+
+    state
+        .outbound_limiter
+        .try_acquire(&format!("{provider}:{to}"))
+        .map_err(OutboundError::RateLimited)?;
+
+    deliver(state, provider, request).await?;
+
+    let delivery_id = Uuid::new_v4().to_string();
+    info!(
+        "Sent proactive {} message to {} via {} (delivery {})",
+        kind, to, provider, delivery_id
+    );
+    Ok(delivery_id)
+}
+
+/// Answers a message the contact just sent, such as an opt-out confirmation: no consent
+/// check and no `MESSAGES_RATE_*` token.
+pub async fn answer(
+    state: &AppState,
+    request: OutboundMessageRequest,
+) -> Result<(), OutboundError> {
+    let provider = validate(state, &request.provider, &request.to, &request.content)?;
+    deliver(state, provider, request).await
+}
+
+async fn deliver(
+    state: &AppState,
+    provider: &'static str,
+    request: OutboundMessageRequest,
+) -> Result<(), OutboundError> {
+    let to = request.to.trim();
+    let kind = request.content.kind();
+
+    // Same lock keys as the inbound handlers of each provider
+    let _guard = state.mutex_swapper.lock(to.to_string()).await;
+
+    match (provider, request.content) {
+        ("waha", content) => {
+            let session = request
+                .session
+                .filter(|s| !s.trim().is_empty())
+                .unwrap_or_else(|| "default".to_string());
+            match content {
+                OutboundContent::Text { text } => send_reply(state, &session, to, text)
+                    .await
+                    .map_err(OutboundError::Send)?,
+                OutboundContent::Media {
+                    url,
+                    mime_type,
+                    filename,
+                    caption,
+                } => {
+                    state
+                        .handoff
+                        .record_sent(to, caption.as_deref().unwrap_or_default());
+                    send_file_message(
+                        &state.http,
+                        &state.cfg,
+                        WahaFileOut {
+                            session,
+                            chat_id: to.to_string(),
+                            file: WahaFile {
+                                mimetype: mime_type,
+                                url,
+                                filename,
+                            },
+                            caption,
+                        },
+                    )
+                    .await
+                    .map_err(OutboundError::Send)?
+                }
+                OutboundContent::Template { .. } => {
+                    return Err(OutboundError::UnsupportedContent(kind, "waha"));
+                }
+            }
+        }
+        ("wacraft", content) => {
+            let client = state
+                .wacraft_client
+                .as_ref()
+                .ok_or(OutboundError::ProviderNotConfigured("wacraft"))?;
+            match content {
+                OutboundContent::Text { text } => {
+                    wacraft::send_reply(state, client, to, &text).await
+                }
+                OutboundContent::Media {
+                    url,
+                    mime_type,
+                    filename,
+                    caption,
+                } => {
+                    state
+                        .handoff
+                        .record_sent(to, caption.as_deref().unwrap_or_default());
+                    client
+                        .send_media_message(
+                            to,
+                            &url,
+                            &mime_type,
+                            filename.as_deref(),
+                            caption.as_deref(),
+                        )
+                        .await
+                }
+                OutboundContent::Template {
+                    name,
+                    language,
+                    components,
+                } => {
+                    client
+                        .send_template_message(to, &name, &language, components)
+                        .await
+                }
+            }
+            .map_err(OutboundError::Send)?
+        }
+        ("matrix", OutboundContent::Text { text }) => state
+            .matrix_client
+            .as_ref()
+            .ok_or(OutboundError::ProviderNotConfigured("matrix"))?
+            .send_text_message(to, &text)
+            .await
+            .map_err(OutboundError::Send)?,
+        ("signal", OutboundContent::Text { text }) => state
+            .signal_client
+            .as_ref()
+            .ok_or(OutboundError::ProviderNotConfigured("signal"))?
+            .send_text_message(to, &text)
+            .await
+            .map_err(OutboundError::Send)?,
+        (provider, _) => return Err(OutboundError::UnsupportedContent(kind, provider)),
+    }
+    Ok(())
+}
+
+/// Checks that `content` can be sent to `to` through `provider`, without sending it.
+/// Returns the provider name.
+pub fn validate(
+    state: &AppState,
+    provider: &str,
+    to: &str,
+    content: &OutboundContent,
+) -> Result<&'static str, OutboundError> {
+    let to = to.trim();
+    if to.is_empty() {
+        return Err(OutboundError::Invalid("recipient is empty"));
+    }
+    match content {
+        OutboundContent::Text { text } if text.trim().is_empty() => {
+            return Err(OutboundError::Invalid("text is empty"));
+        }
+        OutboundContent::Media { url, .. } if url.trim().is_empty() => {
+            return Err(OutboundError::Invalid("media url is empty"));
+        }
+        _ => {}
+    }
+
+    let provider: &'static str = match provider {
+        "waha" => "waha",
+        "wacraft" => "wacraft",
+        "matrix" => "matrix",
+        "signal" => "signal",
+        other => return Err(OutboundError::UnknownProvider(other.to_string())),
+    };
+    let supported = matches!(
+        (provider, content),
+        (_, OutboundContent::Text { .. })
+            | ("wacraft", _)
+            | ("waha", OutboundContent::Media { .. })
+    );
+    if !supported {
+        return Err(OutboundError::UnsupportedContent(content.kind(), provider));
+    }
+    let configured = match provider {
+        "wacraft" => state.wacraft_client.is_some(),
+        "matrix" => state.matrix_client.is_some(),
+        "signal" => state.signal_client.is_some(),
+        _ => true,
+    };
+    if !configured {
+        return Err(OutboundError::ProviderNotConfigured(provider));
+    }
+
+    Ok(provider)
+}
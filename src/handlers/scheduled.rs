@@ -0,0 +1,209 @@
+use super::outbound::{self, OutboundError};
+use crate::{
+    AppState,
+    models::{
+        ai::ScheduledReply,
+        messages::{
+            OutboundContent, OutboundMessageRequest, ScheduleMessageRequest, ScheduledMessage,
+        },
+    },
+    store::schedule::ScheduleStore,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+// Due messages picked up per poll
+const DISPATCH_BATCH: u32 = 50;
+// How long a message being sent stays claimed
+const CLAIM_LEASE_SECS: i64 = 300;
+
+#[derive(Debug, Error)]
+pub enum ScheduleError {
+    #[error(transparent)]
+    Outbound(#[from] OutboundError),
+    #[error("invalid schedule: {0}")]
+    InvalidTime(String),
+    #[error("schedule store error: {0}")]
+    Store(String),
+}
+
+/// Where an agent's `schedule` entries go: the chat the turn came from.
+#[derive(Clone)]
+pub struct ScheduleTarget {
+    pub store: Arc<ScheduleStore>,
+    pub provider: &'static str,
+    pub session: Option<String>,
+    pub to: String,
+}
+
+/// Validates and stores a message for later. It is sent by `run_dispatch_loop` through
+/// the same path as `POST /v1/messages`.
+pub fn schedule(
+    state: &AppState,
+    store: &ScheduleStore,
+    request: ScheduleMessageRequest,
+) -> Result<ScheduledMessage, ScheduleError> {
+    let provider = outbound::validate(state, &request.provider, &request.to, &request.content)?;
+    let send_at = resolve_send_at(request.send_at.as_deref(), request.delay_secs)?;
+    let message = ScheduledMessage {
+        id: Uuid::new_v4().to_string(),
+        provider: provider.to_string(),
+        session: request.session,
+        to: request.to.trim().to_string(),
+        content: request.content,
+        send_at: send_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+        status: "pending".to_string(),
+        attempts: 0,
+        last_error: None,
+    };
+    store.insert(&message).map_err(ScheduleError::Store)?;
+    info!(
+        "Scheduled {} message {} to {} via {} at {}",
+        message.content.kind(),
+        message.id,
+        message.to,
+        message.provider,
+        message.send_at
+    );
+    Ok(message)
+}
+
+/// Stores the `schedule` entries of an agent reply for the chat the turn came from.
+/// Invalid entries are logged and skipped; they never fail the turn.
+pub fn schedule_replies(target: &ScheduleTarget, replies: &[ScheduledReply]) {
+    for reply in replies {
+        if reply.text.trim().is_empty() {
+            warn!("Ignoring empty scheduled reply for {}", target.to);
+            continue;
+        }
+        let send_at = match resolve_send_at(reply.send_at.as_deref(), reply.delay_secs) {
+            Ok(send_at) => send_at,
+            Err(err) => {
+                warn!("Ignoring scheduled reply for {}: {}", target.to, err);
+                continue;
+            }
+        };
+        let message = ScheduledMessage {
+            id: Uuid::new_v4().to_string(),
+            provider: target.provider.to_string(),
+            session: target.session.clone(),
+            to: target.to.clone(),
+            content: OutboundContent::Text {
+                text: reply.text.clone(),
+            },
+            send_at: send_at.to_rfc3339_opts(SecondsFormat::Secs, false),
+            status: "pending".to_string(),
+            attempts: 0,
+            last_error: None,
+        };
+        match target.store.insert(&message) {
+            Ok(()) => info!(
+                "Agent scheduled message {} to {} at {}",
+                message.id, message.to, message.send_at
+            ),
+            Err(err) => warn!("Failed to store scheduled reply for {}: {}", target.to, err),
+        }
+    }
+}
+
+fn resolve_send_at(
+    send_at: Option<&str>,
+    delay_secs: Option<u64>,
+) -> Result<DateTime<Utc>, ScheduleError> {
+    match (send_at, delay_secs) {
+        (Some(at), None) => DateTime::parse_from_rfc3339(at)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|err| ScheduleError::InvalidTime(format!("send_at '{at}': {err}"))),
+        (None, Some(delay)) => i64::try_from(delay)
+            .ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|delay| Utc::now().checked_add_signed(delay))
+            .ok_or_else(|| ScheduleError::InvalidTime(format!("delay_secs {delay} is too large"))),
+        _ => Err(ScheduleError::InvalidTime(
+            "exactly one of send_at and delay_secs is required".to_string(),
+        )),
+    }
+}
+
+/// Sends due messages every `SCHEDULER_POLL_INTERVAL_SECS`. Messages that came due while
+/// the adapter was down go out on the first poll.
+pub async fn run_dispatch_loop(state: AppState, store: Arc<ScheduleStore>) {
+    let cfg = state.cfg.scheduler.clone();
+    let mut interval = tokio::time::interval(Duration::from_secs(cfg.poll_interval_secs.max(1)));
+    info!(
+        "Scheduler started ({}, polling every {}s)",
+        cfg.sqlite_path, cfg.poll_interval_secs
+    );
+
+    loop {
+        interval.tick().await;
+        let due = match store.due(Utc::now(), DISPATCH_BATCH) {
+            Ok(due) => due,
+            Err(err) => {
+                warn!("Failed to load due scheduled messages: {}", err);
+                continue;
+            }
+        };
+
+        // One at a time, so messages to the same contact keep their order.
+        for message in due {
+            let now = Utc::now();
+            match store.claim(
+                &message.id,
+                now,
+                now + chrono::Duration::seconds(CLAIM_LEASE_SECS),
+            ) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    warn!("Failed to claim scheduled message {}: {}", message.id, err);
+                    continue;
+                }
+            }
+            let request = OutboundMessageRequest {
+                provider: message.provider.clone(),
+                session: message.session.clone(),
+                to: message.to.clone(),
+                content: message.content.clone(),
+            };
+            let result = match outbound::send(&state, request).await {
+                Ok(delivery_id) => {
+                    info!(
+                        "Sent scheduled message {} (delivery {})",
+                        message.id, delivery_id
+                    );
+                    store.mark_sent(&message.id)
+                }
+                Err(OutboundError::RateLimited(wait)) => {
+                    let until = chrono::Duration::from_std(wait)
+                        .ok()
+                        .and_then(|wait| Utc::now().checked_add_signed(wait))
+                        .unwrap_or_else(|| {
+                            Utc::now() + chrono::Duration::seconds(cfg.retry_delay_secs as i64)
+                        });
+                    store.postpone(&message.id, until)
+                }
+                Err(err) => {
+                    let attempts = message.attempts + 1;
+                    // An opt-out won't go away by retrying.
+                    let retryable = !matches!(err, OutboundError::OptedOut(_));
+                    let retry_at = (retryable && attempts < cfg.max_attempts).then(|| {
+                        Utc::now() + chrono::Duration::seconds(cfg.retry_delay_secs as i64)
+                    });
+                    warn!(
+                        "Scheduled message {} failed (attempt {}/{}): {}",
+                        message.id, attempts, cfg.max_attempts, err
+                    );
+                    store.mark_attempt_failed(&message.id, &err.to_string(), retry_at)
+                }
+            };
+            if let Err(err) = result {
+                warn!("Failed to update scheduled message {}: {}", message.id, err);
+            }
+        }
+    }
+}
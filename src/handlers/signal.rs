@@ -0,0 +1,240 @@
+use super::AiTurn;
+use crate::{
+    AppState,
+    models::{ai::LlmApiResponse, signal::SignalAttachment},
+    services::{ai::AiError, signal::SignalClient},
+};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+#[derive(Debug, Error)]
+pub enum SignalHandleError {
+    #[error("ai call failed: {0}")]
+    Ai(AiError),
+    #[error("signal api call failed: {0}")]
+    Signal(String),
+    #[error("signal client not configured")]
+    NotConfigured,
+}
+
+// Same idea as the WAHA `TypingGuard`: make sure the typing indicator is cleared.
+struct TypingGuard {
+    client: SignalClient,
+    chat_id: String,
+    stopped: bool,
+}
+
+impl TypingGuard {
+    async fn stop_now(mut self) {
+        if let Err(e) = self.client.set_typing(&self.chat_id, false).await {
+            debug!("Failed to stop Signal typing indicator: {}", e);
+        }
+        self.stopped = true;
+    }
+}
+
+impl Drop for TypingGuard {
+    fn drop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        let client = self.client.clone();
+        let chat_id = self.chat_id.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.set_typing(&chat_id, false).await {
+                debug!("Failed to stop Signal typing indicator: {}", e);
+            }
+        });
+    }
+}
+
+/// Identifies an inbound Signal message: `chat_id` is where replies go (the sender or a
+/// `group.<id>`), `sender` and `sent_at` (ms) are what read receipts refer to.
+pub struct SignalInbound<'a> {
+    pub turn: &'a AiTurn,
+    pub chat_id: &'a str,
+    pub sender: &'a str,
+    pub sent_at: i64,
+}
+
+pub async fn handle_text(
+    state: &AppState,
+    inbound: SignalInbound<'_>,
+    body: &str,
+    attachments: &[SignalAttachment],
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), SignalHandleError> {
+    let client = client(state)?;
+    let timestamp = inbound.sent_at / 1000;
+    let mut data = json!({
+        "text": body,
+        "chat_id": inbound.chat_id,
+        "sender": inbound.sender,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": "signal",
+    });
+    if !attachments.is_empty() {
+        data["attachments"] = attachments_to_value(&client, attachments);
+    }
+
+    respond(
+        state,
+        &client,
+        inbound,
+        data,
+        typing,
+        send_seen,
+        ai_response,
+    )
+    .await
+}
+
+pub async fn handle_attachments(
+    state: &AppState,
+    inbound: SignalInbound<'_>,
+    attachments: &[SignalAttachment],
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), SignalHandleError> {
+    let client = client(state)?;
+    let timestamp = inbound.sent_at / 1000;
+    let data = json!({
+        "attachments": attachments_to_value(&client, attachments),
+        "chat_id": inbound.chat_id,
+        "sender": inbound.sender,
+        "timestamp": timestamp,
+        "current_date": current_date(timestamp),
+        "source": "signal",
+    });
+
+    respond(
+        state,
+        &client,
+        inbound,
+        data,
+        typing,
+        send_seen,
+        ai_response,
+    )
+    .await
+}
+
+async fn respond(
+    state: &AppState,
+    client: &SignalClient,
+    inbound: SignalInbound<'_>,
+    data: Value,
+    typing: bool,
+    send_seen: bool,
+    ai_response: bool,
+) -> Result<(), SignalHandleError> {
+    let cfg = &state.cfg;
+    let chat_id = inbound.chat_id;
+    let _guard = state.mutex_swapper.lock(chat_id.to_string()).await;
+
+    if send_seen {
+        if let Err(err) = client
+            .send_read_receipt(inbound.sender, inbound.sent_at)
+            .await
+        {
+            debug!("Failed to send Signal read receipt: {}", err);
+        }
+    }
+
+    let mut typing_guard = if typing {
+        client
+            .set_typing(chat_id, true)
+            .await
+            .map_err(SignalHandleError::Signal)?;
+        Some(TypingGuard {
+            client: client.clone(),
+            chat_id: chat_id.to_string(),
+            stopped: false,
+        })
+    } else {
+        None
+    };
+
+    let req = inbound.turn.input_request(cfg, data);
+
+    if ai_response {
+        let ai_res: LlmApiResponse = inbound
+            .turn
+            .send(&state.cfg, &req)
+            .await
+            .map_err(SignalHandleError::Ai)?;
+        if let Some(job_id) = &ai_res.job_id {
+            // The reply comes through the AI callback; the typing guard is dropped here.
+            let owned_state = state.clone();
+            let owned_client = client.clone();
+            let owned_chat_id = chat_id.to_string();
+            inbound
+                .turn
+                .defer(state, job_id, chat_id, move |replies| async move {
+                    let _guard = owned_state.mutex_swapper.lock(owned_chat_id.clone()).await;
+                    for reply in replies {
+                        if let Err(err) =
+                            owned_client.send_text_message(&owned_chat_id, &reply).await
+                        {
+                            warn!(
+                                "Failed to send deferred reply to {}: {}",
+                                owned_chat_id, err
+                            );
+                            return;
+                        }
+                    }
+                });
+            return Ok(());
+        }
+
+        for reply in ai_res.replies() {
+            if let Some(guard) = typing_guard.take() {
+                guard.stop_now().await;
+            }
+
+            client
+                .send_text_message(chat_id, &reply)
+                .await
+                .map_err(SignalHandleError::Signal)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn client(state: &AppState) -> Result<SignalClient, SignalHandleError> {
+    state
+        .signal_client
+        .as_ref()
+        .cloned()
+        .ok_or(SignalHandleError::NotConfigured)
+}
+
+fn attachments_to_value(client: &SignalClient, attachments: &[SignalAttachment]) -> Value {
+    Value::Array(
+        attachments
+            .iter()
+            .map(|attachment| {
+                json!({
+                    "id": attachment.id,
+                    "content_type": attachment.content_type,
+                    "filename": attachment.filename,
+                    "size": attachment.size,
+                    "url": client.attachment_url(&attachment.id).ok().map(|url| url.to_string()),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn current_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or(Utc::now())
+        .to_string()
+}
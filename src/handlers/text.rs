@@ -1,23 +1,25 @@
+use super::{AiTurn, TurnReply};
 use crate::{
     AppState,
     config::Config,
     models::{
-        ai::{InputRequest, LlmApiResponse},
+        ai::LlmApiResponse,
         waha::{WahaSeen, WahaTextOut, WahaTyping},
     },
     services::{
-        ai::send_user_message,
+        ai::AiError,
         waha::{send_seen, send_text_message, start_typing, stop_typing},
     },
 };
 use chrono::{DateTime, Utc};
 use serde_json::json;
 use thiserror::Error;
+use tracing::warn;
 
 #[derive(Debug, Error)]
 pub enum TextHandleError {
     #[error("ai call failed: {0}")]
-    Ai(String),
+    Ai(#[from] AiError),
     #[error("waha call failed: {0}")]
     Waha(String),
 }
@@ -44,6 +46,11 @@ impl TypingGuard {
         self.stopped = true;
         // self is dropped right after; Drop sees stopped=true and does nothing.
     }
+
+    /// Leave the indicator on; whoever delivers the reply later stops it.
+    fn disarm(mut self) {
+        self.stopped = true;
+    }
 }
 
 impl Drop for TypingGuard {
@@ -65,10 +72,11 @@ impl Drop for TypingGuard {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_text(
     state: &AppState,
     session: &str,
-    thread_id: &str,
+    turn: &AiTurn,
     chat_id: &str,
     message_id: &str,
     body: &str,
@@ -131,56 +139,64 @@ pub async fn handle_text(
 
     let datetime = DateTime::from_timestamp(timestamp, 0).unwrap_or(Utc::now());
 
-    let req = InputRequest {
-        data: json!({
+    let req = turn.input_request(
+        cfg,
+        json!({
             "text": body,
             // "source": "waha",
             // "chat_id": chat_id,
             // "timestamp": timestamp,
             "current_date": datetime.to_string(),
         }),
-        chat_interface: cfg.chat_interface.clone(),
-        max_retries: cfg.max_retries,
-        loop_threshold: cfg.loop_threshold,
-        top_k: cfg.top_k,
-        summarize_message_window: cfg.summarize_message_window,
-        summarize_message_keep: cfg.summarize_message_keep,
-        summarize_system_messages: cfg.summarize_system_messages,
-        thread_id: thread_id.to_string(),
-    };
+    );
 
     if ai_response {
-        let ai_res: LlmApiResponse = send_user_message(&state.http, cfg, &req)
-            .await
-            .map_err(TextHandleError::Ai)?; // If this fails, the guard is dropped here!
+        // Streamed chunks go out as they are ready, with typing turned back on in between.
+        let replies = turn
+            .stream(cfg, &req, |chunk| async move {
+                send_reply(state, session, chat_id, chunk)
+                    .await
+                    .map_err(TextHandleError::Waha)?;
+                if typing {
+                    let payload = WahaTyping {
+                        chat_id: chat_id.to_string(),
+                        session: session.to_string(),
+                    };
+                    if let Err(e) = start_typing(&state.http, cfg, payload).await {
+                        warn!("Failed to restart typing indicator: {}", e);
+                    }
+                }
+                Ok::<_, TextHandleError>(())
+            })
+            .await?; // If this fails, the guard is dropped here!
+        let replies = match replies {
+            TurnReply::Replies(replies) => replies,
+            TurnReply::Deferred(job_id) => {
+                defer_reply(state, turn, session, chat_id, &job_id, typing_guard);
+                return Ok(());
+            }
+        };
 
-        if let Some(reply) = ai_res.response {
+        for reply in replies {
             // ⬇️ Ensure we stop typing *before* we send the message
             if let Some(guard) = typing_guard.take() {
                 guard.stop_now().await; // awaited: indicator is cleared first
             }
 
-            send_text_message(
-                &state.http,
-                cfg,
-                WahaTextOut {
-                    chat_id: chat_id.to_string(),
-                    text_body: reply,
-                    session: session.to_string(),
-                },
-            )
-            .await
-            .map_err(TextHandleError::Waha)?; // Or here!
+            send_reply(state, session, chat_id, reply)
+                .await
+                .map_err(TextHandleError::Waha)?; // Or here!
         }
     }
 
     Ok(()) // If successful, the guard is dropped here as the function returns.
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_unsupported(
     state: &AppState,
     session: &str,
-    thread_id: &str,
+    turn: &AiTurn,
     chat_id: &str,
     message_id: &str,
     message_type: &str,
@@ -244,48 +260,107 @@ pub async fn handle_unsupported(
 
     let datetime = DateTime::from_timestamp(timestamp, 0).unwrap_or(Utc::now());
 
-    let req = InputRequest {
-        data: json!({
+    let req = turn.input_request(
+        cfg,
+        json!({
             "unsupported_message_type": message_type,
             "source": "waha",
             "chat_id": chat_id,
             "timestamp": timestamp,
             "datetime": datetime.to_string(),
         }),
-        chat_interface: cfg.chat_interface.clone(),
-        max_retries: cfg.max_retries,
-        loop_threshold: cfg.loop_threshold,
-        top_k: cfg.top_k,
-        summarize_message_window: cfg.summarize_message_window,
-        summarize_message_keep: cfg.summarize_message_keep,
-        summarize_system_messages: cfg.summarize_system_messages,
-        thread_id: thread_id.to_string(),
-    };
+    );
 
     if ai_response {
-        let ai_res: LlmApiResponse = send_user_message(&state.http, cfg, &req)
+        let ai_res: LlmApiResponse = turn
+            .send(&state.cfg, &req)
             .await
             .map_err(TextHandleError::Ai)?;
+        if let Some(job_id) = &ai_res.job_id {
+            defer_reply(state, turn, session, chat_id, job_id, typing_guard);
+            return Ok(());
+        }
 
-        if let Some(reply) = ai_res.response {
+        for reply in ai_res.replies() {
             // ⬇️ Ensure we stop typing *before* we send the message
             if let Some(guard) = typing_guard.take() {
                 guard.stop_now().await; // awaited: indicator is cleared first
             }
 
-            send_text_message(
-                &state.http,
-                cfg,
-                WahaTextOut {
-                    chat_id: chat_id.to_string(),
-                    text_body: reply,
-                    session: session.to_string(),
-                },
-            )
-            .await
-            .map_err(TextHandleError::Waha)?;
+            send_reply(state, session, chat_id, reply)
+                .await
+                .map_err(TextHandleError::Waha)?;
         }
     }
 
     Ok(())
 }
+
+/// The agent answers later through the AI callback; typing stays on until then.
+fn defer_reply(
+    state: &AppState,
+    turn: &AiTurn,
+    session: &str,
+    chat_id: &str,
+    job_id: &str,
+    typing_guard: Option<TypingGuard>,
+) {
+    let typing = typing_guard.is_some();
+    if let Some(guard) = typing_guard {
+        guard.disarm();
+    }
+    let owned_state = state.clone();
+    let session = session.to_string();
+    let owned_chat_id = chat_id.to_string();
+    turn.defer(state, job_id, chat_id, move |replies| {
+        deliver_deferred(owned_state, session, owned_chat_id, typing, replies)
+    });
+}
+
+async fn deliver_deferred(
+    state: AppState,
+    session: String,
+    chat_id: String,
+    typing: bool,
+    replies: Vec<String>,
+) {
+    let cfg = &state.cfg;
+    let _guard = state.mutex_swapper.lock(chat_id.clone()).await;
+
+    if typing {
+        let payload = WahaTyping {
+            chat_id: chat_id.clone(),
+            session: session.clone(),
+        };
+        if let Err(e) = stop_typing(&state.http, cfg, payload).await {
+            warn!("Failed to stop typing indicator: {}", e);
+        }
+    }
+
+    for reply in replies {
+        if let Err(e) = send_reply(&state, &session, &chat_id, reply).await {
+            warn!("Failed to send deferred reply to {}: {}", chat_id, e);
+            return;
+        }
+    }
+}
+
+/// Sends a WAHA text, remembering it so its `fromMe` echo isn't taken for an operator.
+pub async fn send_reply(
+    state: &AppState,
+    session: &str,
+    chat_id: &str,
+    text: String,
+) -> Result<(), String> {
+    state.handoff.record_sent(chat_id, &text);
+    send_text_message(
+        &state.http,
+        &state.cfg,
+        WahaTextOut {
+            chat_id: chat_id.to_string(),
+            text_body: text,
+            session: session.to_string(),
+        },
+    )
+    .await
+}
@@ -1,27 +1,29 @@
+use super::{AiTurn, TurnReply};
 use crate::{
     AppState,
-    models::ai::{InputRequest, LlmApiResponse},
-    services::ai::send_user_message,
+    models::ai::LlmApiResponse,
+    services::{ai::AiError, wacraft::WacraftClient},
 };
 use chrono::{DateTime, Utc};
 use serde_json::json;
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, warn};
 
 #[derive(Debug, Error)]
 pub enum WacraftHandleError {
     #[error("ai call failed: {0}")]
-    Ai(String),
+    Ai(#[from] AiError),
     #[error("wacraft api call failed: {0}")]
     Wacraft(String),
     #[error("wacraft client not configured")]
     NotConfigured,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_text(
     state: &AppState,
     session: &str,
-    thread_id: &str,
+    turn: &AiTurn,
     chat_id: &str,
     message_id: &str,
     body: &str,
@@ -50,8 +52,9 @@ pub async fn handle_text(
 
     let datetime = DateTime::from_timestamp(timestamp, 0).unwrap_or(Utc::now());
 
-    let req = InputRequest {
-        data: json!({
+    let req = turn.input_request(
+        cfg,
+        json!({
             "text": body,
             "chat_id": chat_id,
             "session": session,
@@ -59,24 +62,27 @@ pub async fn handle_text(
             "current_date": datetime.to_string(),
             "source": "wacraft",
         }),
-        chat_interface: cfg.chat_interface.clone(),
-        max_retries: cfg.max_retries,
-        loop_threshold: cfg.loop_threshold,
-        top_k: cfg.top_k,
-        summarize_message_window: cfg.summarize_message_window,
-        summarize_message_keep: cfg.summarize_message_keep,
-        summarize_system_messages: cfg.summarize_system_messages,
-        thread_id: thread_id.to_string(),
-    };
+    );
 
     if ai_response {
-        let ai_res: LlmApiResponse = send_user_message(&state.http, cfg, &req)
-            .await
-            .map_err(WacraftHandleError::Ai)?;
-
-        if let Some(reply) = ai_res.response {
-            client
-                .send_text_message(chat_id, &reply)
+        let client = &client;
+        let replies = turn
+            .stream(cfg, &req, |chunk| async move {
+                send_reply(state, client, chat_id, &chunk)
+                    .await
+                    .map_err(WacraftHandleError::Wacraft)
+            })
+            .await?;
+        let replies = match replies {
+            TurnReply::Replies(replies) => replies,
+            TurnReply::Deferred(job_id) => {
+                defer_reply(state, turn, client, chat_id, &job_id);
+                return Ok(());
+            }
+        };
+
+        for reply in replies {
+            send_reply(state, client, chat_id, &reply)
                 .await
                 .map_err(WacraftHandleError::Wacraft)?;
         }
@@ -85,10 +91,11 @@ pub async fn handle_text(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_unsupported(
     state: &AppState,
     session: &str,
-    thread_id: &str,
+    turn: &AiTurn,
     chat_id: &str,
     message_id: &str,
     message_type: &str,
@@ -117,8 +124,9 @@ pub async fn handle_unsupported(
 
     let datetime = DateTime::from_timestamp(timestamp, 0).unwrap_or(Utc::now());
 
-    let req = InputRequest {
-        data: json!({
+    let req = turn.input_request(
+        cfg,
+        json!({
             "unsupported_message_type": message_type,
             "chat_id": chat_id,
             "session": session,
@@ -126,24 +134,20 @@ pub async fn handle_unsupported(
             "current_date": datetime.to_string(),
             "source": "wacraft",
         }),
-        chat_interface: cfg.chat_interface.clone(),
-        max_retries: cfg.max_retries,
-        loop_threshold: cfg.loop_threshold,
-        top_k: cfg.top_k,
-        summarize_message_window: cfg.summarize_message_window,
-        summarize_message_keep: cfg.summarize_message_keep,
-        summarize_system_messages: cfg.summarize_system_messages,
-        thread_id: thread_id.to_string(),
-    };
+    );
 
     if ai_response {
-        let ai_res: LlmApiResponse = send_user_message(&state.http, cfg, &req)
+        let ai_res: LlmApiResponse = turn
+            .send(&state.cfg, &req)
             .await
             .map_err(WacraftHandleError::Ai)?;
+        if let Some(job_id) = &ai_res.job_id {
+            defer_reply(state, turn, &client, chat_id, job_id);
+            return Ok(());
+        }
 
-        if let Some(reply) = ai_res.response {
-            client
-                .send_text_message(chat_id, &reply)
+        for reply in ai_res.replies() {
+            send_reply(state, &client, chat_id, &reply)
                 .await
                 .map_err(WacraftHandleError::Wacraft)?;
         }
@@ -151,3 +155,39 @@ pub async fn handle_unsupported(
 
     Ok(())
 }
+
+/// The agent answers later through the AI callback.
+fn defer_reply(
+    state: &AppState,
+    turn: &AiTurn,
+    client: &WacraftClient,
+    chat_id: &str,
+    job_id: &str,
+) {
+    let owned_state = state.clone();
+    let client = client.clone();
+    let owned_chat_id = chat_id.to_string();
+    turn.defer(state, job_id, chat_id, move |replies| async move {
+        let _guard = owned_state.mutex_swapper.lock(owned_chat_id.clone()).await;
+        for reply in replies {
+            if let Err(err) = send_reply(&owned_state, &client, &owned_chat_id, &reply).await {
+                warn!(
+                    "Failed to send deferred reply to {}: {}",
+                    owned_chat_id, err
+                );
+                return;
+            }
+        }
+    });
+}
+
+/// Sends a Wacraft text, remembering it so its outbound event isn't taken for an operator.
+pub async fn send_reply(
+    state: &AppState,
+    client: &WacraftClient,
+    chat_id: &str,
+    text: &str,
+) -> Result<(), String> {
+    state.handoff.record_sent(chat_id, text);
+    client.send_text_message(chat_id, text).await
+}
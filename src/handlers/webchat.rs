@@ -0,0 +1,85 @@
+use super::AiTurn;
+use crate::{
+    AppState,
+    models::{ai::LlmApiResponse, webchat::WebChatFrame},
+    services::ai::{AiError, router::RouteKey},
+    utils::thread_id_for_webchat,
+};
+use chrono::Utc;
+use serde_json::json;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WebChatHandleError {
+    #[error("ai call failed: {0}")]
+    Ai(AiError),
+}
+
+/// Typing frames stand in for WAHA's typing calls; the reply is delivered as a frame
+/// (or buffered by the hub if the widget is reconnecting).
+pub async fn handle_text(
+    state: &AppState,
+    session: &str,
+    body: &str,
+) -> Result<(), WebChatHandleError> {
+    let cfg = &state.cfg;
+    let hub = &state.webchat;
+    let turn = AiTurn::resolve(
+        state,
+        RouteKey {
+            provider: "webchat",
+            ..Default::default()
+        },
+        session,
+        thread_id_for_webchat(cfg, session),
+    )
+    .await;
+    if turn.is_paused().await || !super::admit(state, &turn.chat, body) {
+        return Ok(());
+    }
+
+    let _guard = state.mutex_swapper.lock(turn.thread_id.clone()).await;
+
+    hub.send(session, WebChatFrame::Typing { active: true });
+
+    let now = Utc::now();
+    let req = turn.input_request(
+        cfg,
+        json!({
+            "text": body,
+            "chat_id": session,
+            "timestamp": now.timestamp(),
+            "current_date": now.to_string(),
+            "source": "webchat",
+        }),
+    );
+
+    let result = turn.send(cfg, &req).await;
+    if let Ok(LlmApiResponse {
+        job_id: Some(job_id),
+        ..
+    }) = &result
+    {
+        // Typing stays on; the reply comes through the AI callback.
+        let owned_state = state.clone();
+        let owned_session = session.to_string();
+        let lock_key = turn.thread_id.clone();
+        turn.defer(state, job_id, session, move |replies| async move {
+            let _guard = owned_state.mutex_swapper.lock(lock_key).await;
+            let hub = &owned_state.webchat;
+            hub.send(&owned_session, WebChatFrame::Typing { active: false });
+            for reply in replies {
+                hub.send(&owned_session, WebChatFrame::Reply { text: reply });
+            }
+        });
+        return Ok(());
+    }
+    hub.send(session, WebChatFrame::Typing { active: false });
+
+    let ai_res: LlmApiResponse = result.map_err(WebChatHandleError::Ai)?;
+    for reply in ai_res.replies() {
+        hub.send(session, WebChatFrame::Reply { text: reply });
+    }
+
+    Ok(())
+}
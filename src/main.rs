@@ -2,18 +2,43 @@ mod apidoc;
 mod config;
 mod handlers;
 mod models;
+mod receivers;
 mod routes;
 mod services;
+mod store;
 mod synch;
 mod utils;
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{Router, routing::post};
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::HeaderMap,
+    routing::{delete, get, post},
+};
 use config::Config;
-use reqwest;
-use services::wacraft::WacraftClient;
-use synch::mutex_swapper::MutexSwapper;
+use services::{
+    access::AccessControl,
+    ai::{build_router, jobs::AiJobs, router::AiRouter},
+    consent::Consent,
+    flood::FloodGuard,
+    handoff::Handoff,
+    loops::LoopDetector,
+    matrix::MatrixClient,
+    moderation::Moderator,
+    redaction::Redactor,
+    signal::SignalClient,
+    wacraft::WacraftClient,
+    webchat::WebChatHub,
+};
+use store::{
+    schedule::ScheduleStore,
+    threads::{ThreadStore, build_thread_store},
+};
+use synch::{mutex_swapper::MutexSwapper, rate_limiter::RateLimiter};
 use tokio::net::TcpListener;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::OpenApi;
@@ -23,8 +48,21 @@ use utoipa_swagger_ui::SwaggerUi;
 pub struct AppState {
     pub cfg: Config,
     pub http: reqwest::Client,
+    pub ai: AiRouter,
+    pub ai_jobs: Arc<AiJobs>,
     pub mutex_swapper: Arc<MutexSwapper<String>>,
+    pub outbound_limiter: Arc<RateLimiter<String>>,
+    pub scheduler: Option<Arc<ScheduleStore>>,
+    pub handoff: Arc<Handoff>,
+    pub access: Arc<AccessControl>,
+    pub consent: Arc<Consent>,
+    pub flood: Arc<FloodGuard>,
+    pub loops: Arc<LoopDetector>,
+    pub threads: Arc<dyn ThreadStore>,
     pub wacraft_client: Option<WacraftClient>,
+    pub matrix_client: Option<MatrixClient>,
+    pub signal_client: Option<SignalClient>,
+    pub webchat: WebChatHub,
 }
 
 #[tokio::main]
@@ -41,22 +79,155 @@ async fn main() {
     // Create a new instance of the MutexSwapper
     let mutex_swapper = Arc::new(MutexSwapper::new());
 
+    // Per-recipient budget for proactive sends (`/v1/messages`)
+    let outbound_limiter = Arc::new(RateLimiter::new(
+        cfg.messages_api.rate_burst,
+        cfg.messages_api.rate_per_minute,
+    ));
+
     let wacraft_client = cfg
         .wacraft
         .as_ref()
         .map(|settings| WacraftClient::new(settings.clone(), http.clone()));
 
+    let matrix_client = cfg
+        .matrix
+        .as_ref()
+        .map(|settings| MatrixClient::new(settings.clone(), http.clone()));
+
+    let signal_client = cfg
+        .signal
+        .as_ref()
+        .map(|settings| SignalClient::new(settings.clone(), http.clone()));
+
+    let webchat = WebChatHub::new(&cfg.webchat);
+
+    let handoff = Arc::new(
+        Handoff::new(&cfg.handoff, &cfg.control_store).expect("Failed to open the pause store"),
+    );
+
+    let access = Arc::new(
+        AccessControl::new(&cfg.access_lists, &cfg.control_store)
+            .expect("Failed to open the access rule store"),
+    );
+
+    let consent = Arc::new(
+        Consent::new(&cfg.consent, &cfg.control_store).expect("Failed to open the consent store"),
+    );
+
+    let flood = Arc::new(FloodGuard::new(&cfg.inbound_limits));
+
+    let loops = Arc::new(LoopDetector::new(&cfg.bot_loops));
+
+    let threads = build_thread_store(&cfg.control_store).expect("Failed to open the thread store");
+
+    let scheduler = cfg.scheduler.enabled.then(|| {
+        Arc::new(
+            ScheduleStore::open(&cfg.scheduler.sqlite_path)
+                .expect("Failed to open the scheduler store"),
+        )
+    });
+
+    let redactor = Redactor::new(&cfg.redaction)
+        .expect("Failed to set up PII redaction")
+        .map(Arc::new);
+
+    let moderator = Moderator::new(&cfg, http.clone())
+        .expect("Failed to set up content moderation")
+        .map(Arc::new);
+
+    let ai = build_router(&cfg, moderator, redactor).expect("Failed to set up the AI backends");
+    let ai_jobs = AiJobs::new(Duration::from_secs(cfg.ai_job_timeout_secs));
+
     // Now build state and move it into the app (no clone needed)
     let state = AppState {
         cfg,
         http,
+        ai,
+        ai_jobs,
         mutex_swapper,
+        outbound_limiter,
+        scheduler,
+        handoff,
+        access,
+        consent,
+        flood,
+        loops,
+        threads,
         wacraft_client,
+        matrix_client,
+        signal_client,
+        webchat,
     };
 
-    let app = Router::new()
+    // Matrix has no webhooks: a background task long-polls `/sync` instead.
+    if let Some(client) = state.matrix_client.clone() {
+        tokio::spawn(receivers::matrix::run_sync_loop(state.clone(), client));
+    }
+
+    // Same for Signal: signal-cli-rest-api is polled through `/v1/receive`.
+    if let Some(client) = state.signal_client.clone() {
+        tokio::spawn(receivers::signal::run_receive_loop(state.clone(), client));
+    }
+
+    // Scheduled messages are sent by a background task as well.
+    if let Some(store) = state.scheduler.clone() {
+        tokio::spawn(handlers::scheduled::run_dispatch_loop(state.clone(), store));
+    }
+
+    let mut app = Router::new()
         .route("/webhooks/waha", post(routes::waha::receive_waha))
         .route("/webhooks/wacraft", post(routes::wacraft::receive_wacraft))
+        .route("/v1/chat", post(routes::chat::chat))
+        .route("/v1/chat/stream", post(routes::chat::chat_stream))
+        .route("/ws/chat", get(routes::webchat::ws_chat))
+        .route("/v1/messages", post(routes::messages::send_message))
+        .route(
+            "/admin/pauses",
+            get(routes::admin::list_pauses)
+                .post(routes::admin::pause)
+                .delete(routes::admin::resume),
+        )
+        .route(
+            "/admin/access",
+            get(routes::admin::list_access_rules)
+                .post(routes::admin::add_access_rule)
+                .delete(routes::admin::remove_access_rule),
+        )
+        .route("/admin/locks", get(routes::admin::list_locks))
+        .route("/admin/threads/reset", post(routes::admin::reset_thread))
+        .route(
+            "/v1/messages/scheduled",
+            post(routes::messages::schedule_message).get(routes::messages::list_scheduled),
+        )
+        .route(
+            "/v1/messages/scheduled/{id}",
+            delete(routes::messages::cancel_scheduled),
+        )
+        .route(
+            "/v1/callbacks/ai/{job_id}",
+            post(routes::callbacks::ai_callback),
+        );
+
+    // Config-defined providers each get their own route.
+    for provider in state.cfg.generic_providers.clone() {
+        tracing::info!(
+            "Registering generic provider '{}' on {}",
+            provider.name,
+            provider.path
+        );
+        let path = provider.path.clone();
+        app = app.route(
+            &path,
+            post(
+                move |State(state): State<AppState>, headers: HeaderMap, body: Bytes| {
+                    routes::generic::receive_generic(state, provider.clone(), headers, body)
+                },
+            ),
+        );
+    }
+
+    let app = app
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", apidoc::ApiDoc::openapi()))
         .with_state(state);
 
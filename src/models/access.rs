@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessListKind {
+    Allow,
+    Deny,
+}
+
+impl AccessListKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccessListKind::Allow => "allow",
+            AccessListKind::Deny => "deny",
+        }
+    }
+}
+
+/// One pattern of an allow or block list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AccessRule {
+    /// `waha`, `wacraft`, `matrix`, `signal` or a generic provider name
+    pub provider: String,
+    /// WAHA session or Wacraft messaging product; `None` applies to every session
+    pub session: Option<String>,
+    pub list: AccessListKind,
+    /// Chat id; `*` matches any run of characters, e.g. `5511*` or `*@g.us`
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccessRules {
+    /// From `ACCESS_LISTS_PATH`; read-only
+    pub config: Vec<AccessRule>,
+    /// Added through the admin API
+    pub admin: Vec<AccessRule>,
+}
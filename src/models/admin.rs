@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Pauses AI replies for one chat, or for every chat of a session when `chat_id` is omitted.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PauseRequest {
+    /// `waha`, `wacraft`, `matrix`, `signal` or a generic provider name
+    pub provider: String,
+    /// WAHA session or Wacraft messaging product
+    pub session: Option<String>,
+    pub chat_id: Option<String>,
+    /// Omitted or 0 pauses until resumed
+    pub ttl_secs: Option<u64>,
+    /// Defaults to `admin`
+    pub reason: Option<String>,
+}
+
+/// A chat lock that is held or waited on: a turn for this chat is in progress.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LockInfo {
+    /// Chat id, room id or (for `/v1/chat`) thread id
+    pub key: String,
+    pub locked: bool,
+    /// Turns queued behind the current one
+    pub waiting: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ThreadResetRequest {
+    /// Thread id as built from the provider prefix, e.g. `waha:5511999999999@c.us`
+    pub thread_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ThreadReset {
+    pub thread_id: String,
+    /// The id the agent sees from now on
+    pub current_thread_id: String,
+}
@@ -12,6 +12,9 @@ pub struct InputRequest<T = serde_json::Value> {
     pub summarize_message_keep: u32,
     pub summarize_system_messages: bool,
     pub thread_id: String,
+    /// Earlier turns of the thread, filled in when the adapter keeps the history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<HistoryTurn>>,
 }
 
 /// Doc-friendly, non-generic version for schema generation.
@@ -26,6 +29,23 @@ pub struct InputRequestDoc {
     pub summarize_message_keep: u32,
     pub summarize_system_messages: bool,
     pub thread_id: String,
+    pub history: Option<Vec<HistoryTurn>>,
+}
+
+/// One stored message of a thread. `role` is `user`, `assistant` or `system` (summaries).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct HistoryTurn {
+    pub role: String,
+    pub content: String,
+}
+
+impl HistoryTurn {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -34,4 +54,45 @@ pub struct LlmApiResponse {
     pub next_step_reason: String,
     /// Optional in our tolerant runtime handling
     pub response: Option<String>,
+    /// Separate outbound messages, when the backend produced more than one
+    /// (`response` then holds them joined, for clients that only read `response`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+    /// Set when the agent accepted the turn as a background job (`202`); the reply arrives
+    /// later through `POST /v1/callbacks/ai/{job_id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<String>,
+    /// Messages the agent wants sent to the same chat later ("remind me tomorrow at 9")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduledReply>,
+    /// Hand the chat over to a human: AI replies are paused after this one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub handoff: bool,
+}
+
+/// One entry of `LlmApiResponse.schedule`. Exactly one of `send_at` and `delay_secs` is
+/// required.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledReply {
+    pub text: String,
+    /// RFC 3339 time
+    pub send_at: Option<String>,
+    /// Seconds from now
+    pub delay_secs: Option<u64>,
+}
+
+/// Body of an agent's `202 Accepted`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AiJobAccepted {
+    pub job_id: String,
+}
+
+impl LlmApiResponse {
+    /// The messages to send back to the user, in order.
+    pub fn replies(&self) -> Vec<String> {
+        if !self.parts.is_empty() {
+            return self.parts.clone();
+        }
+        self.response.iter().cloned().collect()
+    }
 }
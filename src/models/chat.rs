@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::ai::LlmApiResponse;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChatRequest {
+    /// Stable id of the app user; the thread id is `THREAD_PREFIX_CHAT + user_id`.
+    pub user_id: String,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<ChatAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChatAttachment {
+    pub url: String,
+    pub mime_type: Option<String>,
+    pub filename: Option<String>,
+}
+
+/// Frames of the `/v1/chat/stream` SSE response; the SSE event name is the `type`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatEvent {
+    Status { status: String },
+    Typing { active: bool },
+    Reply { reply: LlmApiResponse },
+    Error { error: String },
+}
+
+impl ChatEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChatEvent::Status { .. } => "status",
+            ChatEvent::Typing { .. } => "typing",
+            ChatEvent::Reply { .. } => "reply",
+            ChatEvent::Error { .. } => "error",
+        }
+    }
+}
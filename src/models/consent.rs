@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentStatus {
+    OptedIn,
+    OptedOut,
+}
+
+impl ConsentStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConsentStatus::OptedIn => "opted_in",
+            ConsentStatus::OptedOut => "opted_out",
+        }
+    }
+}
+
+/// The last opt-out or opt-in keyword a contact sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentRecord {
+    /// `waha`, `wacraft`, `matrix`, `signal` or a generic provider name
+    pub provider: String,
+    pub chat_id: String,
+    pub status: ConsentStatus,
+    /// The keyword as the contact sent it
+    pub keyword: String,
+    /// RFC 3339
+    pub updated_at: String,
+}
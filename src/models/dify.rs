@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Dify `POST /chat-messages` in blocking mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct DifyChatRequest<'a> {
+    pub inputs: Value,
+    pub query: &'a str,
+    pub response_mode: &'static str,
+    /// Empty on the first turn; Dify assigns the id and returns it.
+    pub conversation_id: &'a str,
+    pub user: &'a str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DifyChatResponse {
+    #[serde(default)]
+    pub answer: String,
+    pub conversation_id: Option<String>,
+}
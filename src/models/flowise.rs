@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Flowise `POST /api/v1/prediction/{chatflow_id}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowisePredictionRequest<'a> {
+    pub question: &'a str,
+    pub override_config: FlowiseOverrideConfig<'a>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowiseOverrideConfig<'a> {
+    pub session_id: &'a str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlowisePredictionResponse {
+    pub text: Option<String>,
+}
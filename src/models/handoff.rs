@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// AI replies are paused for this chat while a human handles it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ChatPause {
+    /// `waha`, `wacraft`, `matrix`, `signal` or a generic provider name
+    pub provider: String,
+    /// WAHA session or Wacraft messaging product; `None` for providers without sessions
+    pub session: Option<String>,
+    pub chat_id: String,
+    /// Why the bot was paused (`operator`, `agent`, `loop`, ...)
+    pub reason: String,
+    /// RFC 3339
+    pub paused_at: String,
+    /// RFC 3339; `None` pauses until resumed
+    pub until: Option<String>,
+}
+
+/// Posted to `BOT_LOOP_ALERT_URL` when a chat was paused because the bot seems to be
+/// talking to another bot.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoopAlert {
+    /// Always `bot_loop`
+    pub event: &'static str,
+    /// Which check tripped, e.g. `12 messages in 60s`
+    pub reason: String,
+    pub pause: ChatPause,
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Subset of the client-server `/sync` response we care about.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatrixSyncResponse {
+    pub next_batch: String,
+    #[serde(default)]
+    pub rooms: Option<MatrixRooms>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MatrixRooms {
+    #[serde(default)]
+    pub join: HashMap<String, MatrixJoinedRoom>,
+    #[serde(default)]
+    pub invite: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MatrixJoinedRoom {
+    #[serde(default)]
+    pub timeline: Option<MatrixTimeline>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MatrixTimeline {
+    #[serde(default)]
+    pub events: Vec<MatrixRoomEvent>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatrixRoomEvent {
+    pub event_id: String,
+    pub sender: String,
+    /// Milliseconds since the unix epoch
+    pub origin_server_ts: i64,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    #[serde(default)]
+    pub content: MatrixMessageContent,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MatrixMessageContent {
+    pub msgtype: Option<String>,
+    pub body: Option<String>,
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatrixWhoAmI {
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatrixTextOut {
+    pub msgtype: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatrixTyping {
+    pub typing: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
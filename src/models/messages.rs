@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// A message the agent (or any backend service) wants to send without a user turn.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutboundMessageRequest {
+    /// `waha`, `wacraft`, `matrix` or `signal`
+    pub provider: String,
+    /// WAHA session (defaults to `default`); ignored by the other providers
+    pub session: Option<String>,
+    /// Chat id (`5511...@c.us`), WhatsApp id, Matrix room id or Signal number/group
+    pub to: String,
+    pub content: OutboundContent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutboundContent {
+    Text {
+        text: String,
+    },
+    /// A file by URL; images, videos and audio are sent as such, anything else as a document
+    Media {
+        url: String,
+        /// e.g. `image/jpeg`, `application/pdf`
+        mime_type: String,
+        filename: Option<String>,
+        caption: Option<String>,
+    },
+    /// WhatsApp Cloud API template (Wacraft only)
+    Template {
+        name: String,
+        language: String,
+        #[serde(default)]
+        components: Vec<Value>,
+    },
+}
+
+impl OutboundContent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OutboundContent::Text { .. } => "text",
+            OutboundContent::Media { .. } => "media",
+            OutboundContent::Template { .. } => "template",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutboundMessageResponse {
+    /// Id of this delivery, also logged with the send
+    pub delivery_id: String,
+}
+
+/// A message to send later. Exactly one of `send_at` and `delay_secs` is required.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleMessageRequest {
+    pub provider: String,
+    pub session: Option<String>,
+    pub to: String,
+    pub content: OutboundContent,
+    /// RFC 3339 time, e.g. `2025-05-01T09:00:00-03:00`
+    pub send_at: Option<String>,
+    /// Seconds from now
+    pub delay_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledMessage {
+    pub id: String,
+    pub provider: String,
+    pub session: Option<String>,
+    pub to: String,
+    pub content: OutboundContent,
+    /// RFC 3339 (UTC); pushed back after a failed attempt
+    pub send_at: String,
+    /// `pending`, or `failed` once `SCHEDULER_MAX_ATTEMPTS` sends failed
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
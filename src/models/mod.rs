@@ -1,4 +1,18 @@
+pub mod access;
+pub mod admin;
 pub mod ai;
+pub mod chat;
 pub mod common;
+pub mod consent;
+pub mod dify;
+pub mod flowise;
+pub mod handoff;
+pub mod matrix;
+pub mod messages;
+pub mod moderation;
+pub mod openai;
+pub mod rasa;
+pub mod signal;
 pub mod wacraft;
 pub mod waha;
+pub mod webchat;
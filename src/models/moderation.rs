@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationDirection {
+    /// A user message, before the AI call
+    Inbound,
+    /// An AI reply, before it is sent
+    Outbound,
+}
+
+/// Posted to `MODERATION_URL` for every text a rule with `http: true` checks.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModerationRequest<'a> {
+    pub direction: ModerationDirection,
+    pub thread_id: &'a str,
+    pub text: &'a str,
+}
+
+/// What the moderation endpoint answers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModerationResult {
+    pub flagged: bool,
+    /// Logged and passed on as `next_step_reason`
+    pub reason: Option<String>,
+    /// A cleaned-up version of a flagged reply, used with `action: rewrite`
+    pub text: Option<String>,
+}
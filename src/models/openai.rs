@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+/// Minimal `/v1/chat/completions` request; extra fields are left to server defaults.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatCompletionRequest<'a> {
+    pub model: &'a str,
+    pub messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionResponse {
+    #[serde(default)]
+    pub choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionChoice {
+    pub message: ChatCompletionMessage,
+    pub finish_reason: Option<String>,
+}
+
+/// `content` is null when the model only returned tool calls.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionMessage {
+    pub content: Option<String>,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Rasa REST channel (`POST /webhooks/rest/webhook`).
+#[derive(Debug, Clone, Serialize)]
+pub struct RasaMessage<'a> {
+    pub sender: &'a str,
+    pub message: &'a str,
+}
+
+/// One bot utterance; a turn answers with a list of these.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RasaBotMessage {
+    pub text: Option<String>,
+    pub image: Option<String>,
+}
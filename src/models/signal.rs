@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One item of the `GET /v1/receive/{number}` response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignalReceived {
+    pub envelope: SignalEnvelope,
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalEnvelope {
+    pub source: Option<String>,
+    pub source_number: Option<String>,
+    pub source_uuid: Option<String>,
+    pub source_name: Option<String>,
+    /// Milliseconds since the unix epoch
+    pub timestamp: i64,
+    pub data_message: Option<SignalDataMessage>,
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalDataMessage {
+    pub timestamp: i64,
+    pub message: Option<String>,
+    pub group_info: Option<SignalGroupInfo>,
+    #[serde(default)]
+    pub attachments: Vec<SignalAttachment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalGroupInfo {
+    /// Internal (base64) group id as reported by signal-cli
+    pub group_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalAttachment {
+    pub id: String,
+    pub content_type: Option<String>,
+    pub filename: Option<String>,
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignalTextOut {
+    pub message: String,
+    pub number: String,
+    pub recipients: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignalTyping {
+    pub recipient: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignalReceipt {
+    pub receipt_type: String,
+    pub recipient: String,
+    pub timestamp: i64,
+}
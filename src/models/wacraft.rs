@@ -10,6 +10,8 @@ pub struct WacraftWebhook {
     pub from_id: Option<String>,
     pub messaging_product_id: Option<String>,
     pub receiver_data: Option<WacraftReceiverData>,
+    /// Set instead of `receiver_data` on messages sent from the business number
+    pub sender_data: Option<WacraftSenderData>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
     pub deleted_at: Option<String>,
@@ -29,6 +31,17 @@ pub struct WacraftReceiverData {
     pub extra: HashMap<String, Value>,
 }
 
+/// An outgoing message, as posted to the Cloud API.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WacraftSenderData {
+    pub to: Option<String>,
+    #[serde(rename = "type")]
+    pub message_type: Option<String>,
+    pub text: Option<WacraftText>,
+    #[serde(flatten, default)]
+    pub extra: HashMap<String, Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct WacraftContext {
     pub forwarded: Option<bool>,
@@ -62,3 +62,21 @@ pub struct WahaTextOut {
     #[serde(rename = "text")]
     pub text_body: String,
 }
+
+#[derive(Debug, Serialize)]
+pub struct WahaFile {
+    pub mimetype: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WahaFileOut {
+    pub session: String,
+    #[serde(rename = "chatId")]
+    pub chat_id: String,
+    pub file: WahaFile,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
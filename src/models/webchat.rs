@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Frames sent by the widget over `/ws/chat`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebChatInbound {
+    Message { text: String },
+    Ping,
+}
+
+/// Frames sent by the adapter over `/ws/chat`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebChatFrame {
+    /// First frame of every connection; reconnect with `?session=<session>` to resume.
+    Session {
+        session: String,
+    },
+    Typing {
+        active: bool,
+    },
+    Reply {
+        text: String,
+    },
+    Error {
+        error: String,
+    },
+    Pong,
+}
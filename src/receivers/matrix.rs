@@ -0,0 +1,129 @@
+use std::{fs, time::Duration};
+
+use tracing::{error, info, warn};
+
+use crate::{AppState, handlers, models::matrix::MatrixRooms, services::matrix::MatrixClient};
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Long-polls the homeserver and feeds `m.room.message` events into the handlers.
+/// Runs forever; spawn it as a background task.
+pub async fn run_sync_loop(state: AppState, client: MatrixClient) {
+    let cfg = client.config().clone();
+
+    let own_user_id = loop {
+        match client.user_id().await {
+            Ok(id) => break id,
+            Err(err) => {
+                error!("Failed to resolve Matrix user id: {}", err);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    };
+
+    let mut since = load_sync_token(&cfg.sync_token_path);
+    while since.is_none() {
+        // First start: skip the existing history and only answer new messages.
+        match client.sync(None, 0).await {
+            Ok(res) => {
+                info!("Initial Matrix sync done, skipping existing history");
+                store_sync_token(&cfg.sync_token_path, &res.next_batch);
+                since = Some(res.next_batch);
+            }
+            Err(err) => {
+                error!("Initial Matrix sync failed: {}", err);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+
+    info!("Matrix sync loop started as {}", own_user_id);
+
+    loop {
+        match client.sync(since.as_deref(), cfg.sync_timeout_ms).await {
+            Ok(res) => {
+                // The token only moves on once the batch is handled, so a crash replays it
+                // instead of losing it.
+                if let Some(rooms) = res.rooms {
+                    process_rooms(&state, &client, &own_user_id, rooms).await;
+                }
+                store_sync_token(&cfg.sync_token_path, &res.next_batch);
+                since = Some(res.next_batch);
+            }
+            Err(err) => {
+                warn!("Matrix sync failed: {}", err);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Handles the events of a batch and returns once all of them are done.
+async fn process_rooms(
+    state: &AppState,
+    client: &MatrixClient,
+    own_user_id: &str,
+    rooms: MatrixRooms,
+) {
+    if client.config().auto_join {
+        for room_id in rooms.invite.keys() {
+            match client.join_room(room_id).await {
+                Ok(()) => info!("Joined Matrix room {}", room_id),
+                Err(err) => warn!("Failed to join Matrix room {}: {}", room_id, err),
+            }
+        }
+    }
+
+    let mut tasks = Vec::new();
+    for (room_id, room) in rooms.join {
+        let events = room
+            .timeline
+            .map(|timeline| timeline.events)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|event| event.sender != own_user_id && event.event_type == "m.room.message")
+            .collect::<Vec<_>>();
+
+        if events.is_empty() {
+            continue;
+        }
+
+        // One task per room keeps events of a room in order while rooms run concurrently.
+        let state = state.clone();
+        let typing = client.config().typing;
+        let send_seen = client.config().send_seen;
+        tasks.push(tokio::spawn(async move {
+            for event in events {
+                let event_id = event.event_id.clone();
+                if let Err(err) =
+                    handlers::dispatch_matrix(&room_id, event, state.clone(), typing, send_seen)
+                        .await
+                {
+                    error!(
+                        "Failed to handle Matrix event {} in {}: {}",
+                        event_id, room_id, err
+                    );
+                }
+            }
+        }));
+    }
+
+    for task in tasks {
+        if let Err(err) = task.await {
+            error!("Matrix room task failed: {}", err);
+        }
+    }
+}
+
+fn load_sync_token(path: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn store_sync_token(path: &str, token: &str) {
+    if let Err(err) = fs::write(path, token) {
+        warn!("Failed to persist Matrix sync token to {}: {}", path, err);
+    }
+}
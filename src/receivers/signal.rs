@@ -0,0 +1,64 @@
+use std::{collections::HashMap, time::Duration};
+
+use tracing::{error, info, warn};
+
+use crate::{AppState, handlers, models::signal::SignalEnvelope, services::signal::SignalClient};
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Polls signal-cli-rest-api for new messages and feeds them into the handlers.
+/// Runs forever; spawn it as a background task.
+pub async fn run_receive_loop(state: AppState, client: SignalClient) {
+    info!("Signal receive loop started for {}", client.config().number);
+
+    loop {
+        match client.receive().await {
+            Ok(received) => {
+                let mut by_chat: HashMap<String, Vec<SignalEnvelope>> = HashMap::new();
+                for item in received {
+                    by_chat
+                        .entry(conversation_key(&item.envelope))
+                        .or_default()
+                        .push(item.envelope);
+                }
+
+                // One task per conversation keeps its messages in order while chats run concurrently.
+                for (key, envelopes) in by_chat {
+                    let state = state.clone();
+                    let typing = client.config().typing;
+                    let send_seen = client.config().send_seen;
+                    tokio::spawn(async move {
+                        for envelope in envelopes {
+                            if let Err(err) = handlers::dispatch_signal(
+                                envelope,
+                                state.clone(),
+                                typing,
+                                send_seen,
+                            )
+                            .await
+                            {
+                                error!("Failed to handle Signal message from {}: {}", key, err);
+                            }
+                        }
+                    });
+                }
+            }
+            Err(err) => {
+                warn!("Signal receive failed: {}", err);
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+fn conversation_key(envelope: &SignalEnvelope) -> String {
+    envelope
+        .data_message
+        .as_ref()
+        .and_then(|message| message.group_info.as_ref())
+        .map(|group| group.group_id.clone())
+        .or_else(|| envelope.source_number.clone())
+        .or_else(|| envelope.source_uuid.clone())
+        .or_else(|| envelope.source.clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,364 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+use tracing::info;
+
+use super::require_bearer;
+use crate::{
+    AppState,
+    models::{
+        access::{AccessListKind, AccessRule, AccessRules},
+        admin::{LockInfo, PauseRequest, ThreadReset, ThreadResetRequest},
+        handoff::ChatPause,
+    },
+    services::handoff::{ChatRef, WHOLE_SESSION},
+    store::threads::current_thread_id,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ResumeParams {
+    pub provider: String,
+    pub session: Option<String>,
+    pub chat_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccessRuleParams {
+    pub provider: String,
+    pub session: Option<String>,
+    pub list: AccessListKind,
+    pub pattern: String,
+}
+
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    require_bearer(headers, state.cfg.admin_api_token.as_deref(), "Admin API")
+}
+
+/// A missing `chat_id` addresses the whole session.
+fn chat_ref(provider: &str, session: Option<&str>, chat_id: Option<&str>) -> ChatRef {
+    let non_empty = |value: Option<&str>| {
+        value
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    ChatRef {
+        provider: provider.trim().to_string(),
+        session: non_empty(session),
+        chat_id: non_empty(chat_id).unwrap_or_else(|| WHOLE_SESSION.to_string()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/pauses",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    responses(
+        (status = 200, description = "Active pauses, oldest first; `chat_id` `*` covers the whole session", body = [ChatPause]),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn list_pauses(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ChatPause>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    state
+        .handoff
+        .list()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/pauses",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    request_body = PauseRequest,
+    responses(
+        (status = 201, description = "Chat or session paused", body = ChatPause),
+        (status = 400, description = "Missing provider or TTL too long", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn pause(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<PauseRequest>,
+) -> Result<(StatusCode, Json<ChatPause>), (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let chat = chat_ref(
+        &request.provider,
+        request.session.as_deref(),
+        request.chat_id.as_deref(),
+    );
+    if chat.provider.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Field 'provider' is required.".to_string(),
+        ));
+    }
+    let reason = request.reason.as_deref().unwrap_or("admin");
+
+    state
+        .handoff
+        .pause_for(&chat, reason, request.ttl_secs.unwrap_or_default())
+        .map(|pause| (StatusCode::CREATED, Json(pause)))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/pauses",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`"),
+        ("provider" = String, Query, description = "Provider of the pause"),
+        ("session" = Option<String>, Query, description = "WAHA session or Wacraft messaging product"),
+        ("chat_id" = Option<String>, Query, description = "Omit to resume a session-wide pause")
+    ),
+    responses(
+        (status = 204, description = "Pause lifted"),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 404, description = "No such pause", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn resume(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ResumeParams>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let chat = chat_ref(
+        &params.provider,
+        params.session.as_deref(),
+        params.chat_id.as_deref(),
+    );
+    match state.handoff.resume(&chat) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "No such pause.".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/locks",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    responses(
+        (status = 200, description = "Chats with a turn in progress", body = [LockInfo]),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn list_locks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LockInfo>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let mut locks: Vec<LockInfo> = state
+        .mutex_swapper
+        .snapshot()
+        .await
+        .into_iter()
+        .map(|lock| LockInfo {
+            key: lock.key,
+            locked: lock.locked,
+            waiting: lock.waiting,
+        })
+        .collect();
+    locks.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(Json(locks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/threads/reset",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    request_body = ThreadResetRequest,
+    responses(
+        (status = 200, description = "Thread reset; the next turn starts a new conversation", body = ThreadReset),
+        (status = 400, description = "Missing thread id", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn reset_thread(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ThreadResetRequest>,
+) -> Result<Json<ThreadReset>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let thread_id = request.thread_id.trim();
+    if thread_id.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Field 'thread_id' is required.".to_string(),
+        ));
+    }
+
+    let generation = state
+        .threads
+        .bump(thread_id)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let current = current_thread_id(thread_id, generation);
+    info!("Reset thread {} (now {})", thread_id, current);
+    Ok(Json(ThreadReset {
+        thread_id: thread_id.to_string(),
+        current_thread_id: current,
+    }))
+}
+
+/// Trims the rule and drops an empty session, so it compares equal to the stored one.
+fn normalize_rule(rule: AccessRule) -> Result<AccessRule, (StatusCode, String)> {
+    let rule = AccessRule {
+        provider: rule.provider.trim().to_string(),
+        session: rule
+            .session
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()),
+        list: rule.list,
+        pattern: rule.pattern.trim().to_string(),
+    };
+    if rule.provider.is_empty() || rule.pattern.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Fields 'provider' and 'pattern' are required.".to_string(),
+        ));
+    }
+    Ok(rule)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/access",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    responses(
+        (status = 200, description = "Allow and block list patterns, from config and from this API", body = AccessRules),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn list_access_rules(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AccessRules>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    state
+        .access
+        .rules()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/access",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`")
+    ),
+    request_body = AccessRule,
+    responses(
+        (status = 201, description = "Pattern added", body = AccessRule),
+        (status = 200, description = "Pattern was already there", body = AccessRule),
+        (status = 400, description = "Missing provider or pattern", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn add_access_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(rule): Json<AccessRule>,
+) -> Result<(StatusCode, Json<AccessRule>), (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let rule = normalize_rule(rule)?;
+    match state.access.add(&rule) {
+        Ok(true) => {
+            info!(
+                "Added {} pattern '{}' for {}",
+                rule.list.as_str(),
+                rule.pattern,
+                rule.provider
+            );
+            Ok((StatusCode::CREATED, Json(rule)))
+        }
+        Ok(false) => Ok((StatusCode::OK, Json(rule))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/access",
+    tag = "admin",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <ADMIN_API_TOKEN>`"),
+        ("provider" = String, Query, description = "Provider of the pattern"),
+        ("session" = Option<String>, Query, description = "Session of the pattern, if any"),
+        ("list" = AccessListKind, Query, description = "`allow` or `deny`"),
+        ("pattern" = String, Query, description = "The pattern, as added")
+    ),
+    responses(
+        (status = 204, description = "Pattern removed"),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 404, description = "No such pattern added through this API", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Admin API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn remove_access_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<AccessRuleParams>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+
+    let rule = normalize_rule(AccessRule {
+        provider: params.provider,
+        session: params.session,
+        list: params.list,
+        pattern: params.pattern,
+    })?;
+    match state.access.remove(&rule) {
+        Ok(true) => {
+            info!(
+                "Removed {} pattern '{}' for {}",
+                rule.list.as_str(),
+                rule.pattern,
+                rule.provider
+            );
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err((StatusCode::NOT_FOUND, "No such pattern.".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
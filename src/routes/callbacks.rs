@@ -0,0 +1,50 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+};
+use tracing::info;
+
+use super::require_bearer;
+use crate::{AppState, models::ai::LlmApiResponse};
+
+#[utoipa::path(
+    post,
+    path = "/v1/callbacks/ai/{job_id}",
+    tag = "callbacks",
+    params(
+        ("job_id" = String, Path, description = "Job id from the agent's `202 Accepted`"),
+        ("Authorization" = String, Header, description = "`Bearer <AI_CALLBACK_TOKEN>`")
+    ),
+    request_body = LlmApiResponse,
+    responses(
+        (status = 202, description = "Reply accepted; it is delivered to the chat that started the job"),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "AI callbacks disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn ai_callback(
+    State(state): State<AppState>,
+    Path(job_id): Path<String>,
+    headers: HeaderMap,
+    Json(res): Json<LlmApiResponse>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    require_bearer(
+        &headers,
+        state.cfg.ai_callback_token.as_deref(),
+        "AI callback endpoint",
+    )?;
+
+    let delivered = state.ai_jobs.complete(&job_id, res);
+    info!(
+        "AI callback for job {} ({})",
+        job_id,
+        if delivered {
+            "delivering"
+        } else {
+            "no turn waiting yet"
+        }
+    );
+
+    Ok(StatusCode::ACCEPTED)
+}
@@ -0,0 +1,103 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use tokio::sync::mpsc;
+use tokio_stream::{Stream, StreamExt, wrappers::ReceiverStream};
+use tracing::info;
+
+use super::require_bearer;
+use crate::{
+    AppState,
+    handlers::{self, chat::ChatHandleError},
+    models::{
+        ai::LlmApiResponse,
+        chat::{ChatEvent, ChatRequest},
+    },
+};
+
+#[utoipa::path(
+    post,
+    path = "/v1/chat",
+    tag = "chat",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <CHAT_API_TOKEN>`")
+    ),
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Agent reply", body = LlmApiResponse),
+        (status = 400, description = "Empty message", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 409, description = "AI replies are paused for this chat", body = crate::models::common::ErrorMessage),
+        (status = 429, description = "Over the inbound limit (`INBOUND_RATE_*`)", body = crate::models::common::ErrorMessage),
+        (status = 502, description = "AI call failed", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Chat API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn chat(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatRequest>,
+) -> Result<Json<LlmApiResponse>, (StatusCode, String)> {
+    require_bearer(&headers, state.cfg.chat_api_token.as_deref(), "Chat API")?;
+
+    info!("Incoming chat message (user_id={})", request.user_id);
+
+    handlers::chat::handle_chat(&state, request, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            let status = match e {
+                ChatHandleError::EmptyMessage => StatusCode::BAD_REQUEST,
+                ChatHandleError::Ai(_) => StatusCode::BAD_GATEWAY,
+                ChatHandleError::Paused => StatusCode::CONFLICT,
+                ChatHandleError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            };
+            (status, format!("handler error: {e}"))
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/chat/stream",
+    tag = "chat",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <CHAT_API_TOKEN>`")
+    ),
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "`text/event-stream` of `status`, `typing`, then `reply` or `error` events", body = ChatEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Chat API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn chat_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ChatRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, (StatusCode, String)> {
+    require_bearer(&headers, state.cfg.chat_api_token.as_deref(), "Chat API")?;
+
+    info!(
+        "Incoming streaming chat message (user_id={})",
+        request.user_id
+    );
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let last = match handlers::chat::handle_chat(&state, request, Some(tx.clone())).await {
+            Ok(reply) => ChatEvent::Reply { reply },
+            Err(e) => ChatEvent::Error {
+                error: e.to_string(),
+            },
+        };
+        let _ = tx.send(last).await;
+    });
+
+    let stream = ReceiverStream::new(rx)
+        .map(|event: ChatEvent| Event::default().event(event.name()).json_data(&event));
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,45 @@
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode},
+};
+use serde_json::Value as JsonValue;
+use tracing::info;
+
+use super::{parse_allowed_ids, parse_bool_header, verify_hmac};
+use crate::{AppState, config::GenericProviderConfig, handlers};
+
+/// Entry point for every config-defined provider. Routes are registered per provider in `main`,
+/// so this is not part of the OpenAPI document.
+pub async fn receive_generic(
+    state: AppState,
+    provider: GenericProviderConfig,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(hmac) = &provider.hmac {
+        verify_hmac(&headers, hmac, &body)?;
+    }
+
+    let payload: JsonValue = serde_json::from_slice(&body).map_err(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Failed to deserialize webhook payload: {err}"),
+        )
+    })?;
+
+    let allowed_wa_ids = parse_allowed_ids(&headers)?;
+    let ai_response = parse_bool_header(&headers, "x-ai-response")?.unwrap_or(true);
+
+    info!("Incoming generic webhook (provider={})", provider.name);
+
+    handlers::dispatch_generic(payload, &provider, state, allowed_wa_ids, ai_response)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("handler error: {e}"),
+            )
+        })?;
+
+    Ok(StatusCode::OK)
+}
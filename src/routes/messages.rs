@@ -0,0 +1,203 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::require_bearer;
+use crate::{
+    AppState,
+    handlers::{
+        outbound::{self, OutboundError},
+        scheduled::{self, ScheduleError},
+    },
+    models::messages::{
+        OutboundMessageRequest, OutboundMessageResponse, ScheduleMessageRequest, ScheduledMessage,
+    },
+    store::schedule::ScheduleStore,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduledParams {
+    pub provider: String,
+    pub to: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/messages",
+    tag = "messages",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <MESSAGES_API_TOKEN>`")
+    ),
+    request_body = OutboundMessageRequest,
+    responses(
+        (status = 200, description = "Message sent", body = OutboundMessageResponse),
+        (status = 400, description = "Invalid message, unknown/unconfigured provider or unsupported content", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 403, description = "Recipient opted out", body = crate::models::common::ErrorMessage),
+        (status = 429, description = "Recipient rate limit reached; see `Retry-After`", body = crate::models::common::ErrorMessage),
+        (status = 502, description = "Provider rejected the message", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Messages API disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn send_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<OutboundMessageRequest>,
+) -> Result<Json<OutboundMessageResponse>, Response> {
+    require_bearer(
+        &headers,
+        state.cfg.messages_api.token.as_deref(),
+        "Messages API",
+    )
+    .map_err(IntoResponse::into_response)?;
+
+    info!(
+        "Proactive {} message requested (provider={}, to={})",
+        request.content.kind(),
+        request.provider,
+        request.to
+    );
+
+    match outbound::send(&state, request).await {
+        Ok(delivery_id) => Ok(Json(OutboundMessageResponse { delivery_id })),
+        Err(OutboundError::RateLimited(wait)) => {
+            let retry_after = (wait.as_secs_f64().ceil() as u64).max(1).to_string();
+            let mut res = (
+                StatusCode::TOO_MANY_REQUESTS,
+                OutboundError::RateLimited(wait).to_string(),
+            )
+                .into_response();
+            if let Ok(value) = HeaderValue::from_str(&retry_after) {
+                res.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            Err(res)
+        }
+        Err(e) => {
+            let status = match e {
+                OutboundError::Send(_) => StatusCode::BAD_GATEWAY,
+                OutboundError::OptedOut(_) => StatusCode::FORBIDDEN,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            warn!("Proactive message failed: {}", e);
+            Err((status, format!("handler error: {e}")).into_response())
+        }
+    }
+}
+
+/// Same token as `/v1/messages`; answers 503 while the scheduler is disabled.
+fn require_scheduler(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Arc<ScheduleStore>, (StatusCode, String)> {
+    require_bearer(
+        headers,
+        state.cfg.messages_api.token.as_deref(),
+        "Messages API",
+    )?;
+    state.scheduler.clone().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Scheduler is disabled.".to_string(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/messages/scheduled",
+    tag = "messages",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <MESSAGES_API_TOKEN>`")
+    ),
+    request_body = ScheduleMessageRequest,
+    responses(
+        (status = 201, description = "Message scheduled", body = ScheduledMessage),
+        (status = 400, description = "Invalid message or time, unknown/unconfigured provider or unsupported content", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Messages API or scheduler disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn schedule_message(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ScheduleMessageRequest>,
+) -> Result<(StatusCode, Json<ScheduledMessage>), (StatusCode, String)> {
+    let store = require_scheduler(&state, &headers)?;
+
+    scheduled::schedule(&state, &store, request)
+        .map(|message| (StatusCode::CREATED, Json(message)))
+        .map_err(|e| {
+            let status = match e {
+                ScheduleError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (status, format!("handler error: {e}"))
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/messages/scheduled",
+    tag = "messages",
+    params(
+        ("Authorization" = String, Header, description = "`Bearer <MESSAGES_API_TOKEN>`"),
+        ("provider" = String, Query, description = "`waha`, `wacraft`, `matrix` or `signal`"),
+        ("to" = String, Query, description = "Recipient, as given when scheduling")
+    ),
+    responses(
+        (status = 200, description = "Pending and failed messages of the contact, soonest first", body = [ScheduledMessage]),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Messages API or scheduler disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn list_scheduled(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ScheduledParams>,
+) -> Result<Json<Vec<ScheduledMessage>>, (StatusCode, String)> {
+    let store = require_scheduler(&state, &headers)?;
+
+    store
+        .list(&params.provider, params.to.trim())
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v1/messages/scheduled/{id}",
+    tag = "messages",
+    params(
+        ("id" = String, Path, description = "Id returned when scheduling"),
+        ("Authorization" = String, Header, description = "`Bearer <MESSAGES_API_TOKEN>`")
+    ),
+    responses(
+        (status = 204, description = "Message cancelled"),
+        (status = 401, description = "Missing or invalid token", body = crate::models::common::ErrorMessage),
+        (status = 404, description = "No scheduled message with this id", body = crate::models::common::ErrorMessage),
+        (status = 503, description = "Messages API or scheduler disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn cancel_scheduled(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let store = require_scheduler(&state, &headers)?;
+
+    match store.cancel(&id) {
+        Ok(true) => {
+            info!("Cancelled scheduled message {}", id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            format!("No scheduled message with id {id}."),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
@@ -1,2 +1,220 @@
+use axum::http::{HeaderMap, StatusCode};
+
+use crate::{
+    config::{HmacConfig, SharedSecretConfig},
+    utils::{constant_time_eq, verify_hmac_hex},
+};
+
+pub mod admin;
+pub mod callbacks;
+pub mod chat;
+pub mod generic;
+pub mod messages;
 pub mod wacraft;
 pub mod waha;
+pub mod webchat;
+
+pub(crate) fn parse_allowed_ids(
+    headers: &HeaderMap,
+) -> Result<Option<Vec<String>>, (StatusCode, String)> {
+    if let Some(ids_header) = headers.get("x-allowed-wa-ids") {
+        let ids_str = ids_header.to_str().map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Header 'x-allowed-wa-ids' contains invalid characters.".to_string(),
+            )
+        })?;
+
+        let ids = ids_str
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        if !ids.is_empty() {
+            return Ok(Some(ids));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn parse_bool_header(
+    headers: &HeaderMap,
+    key: &str,
+) -> Result<Option<bool>, (StatusCode, String)> {
+    if let Some(value) = headers.get(key) {
+        let value = value.to_str().map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Header '{}' contains invalid characters.", key),
+            )
+        })?;
+
+        match value {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => Err((
+                StatusCode::BAD_REQUEST,
+                format!("Header '{}' must be 'true' or 'false'.", key),
+            )),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Checks `Authorization: Bearer <token>` against the configured token.
+/// Endpoints without a configured token are reported as disabled.
+pub(crate) fn require_bearer(
+    headers: &HeaderMap,
+    expected: Option<&str>,
+    what: &str,
+) -> Result<(), (StatusCode, String)> {
+    let Some(expected) = expected else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("{what} is disabled: no token configured."),
+        ));
+    };
+
+    let provided = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid bearer token.".to_string(),
+        )),
+    }
+}
+
+/// Checks the HMAC signature of the raw body; run before the body is deserialized.
+pub(crate) fn verify_hmac(
+    headers: &HeaderMap,
+    hmac: &HmacConfig,
+    body: &[u8],
+) -> Result<(), (StatusCode, String)> {
+    let signature = headers
+        .get(hmac.header.as_str())
+        .and_then(|value| value.to_str().ok())
+        .ok_or((
+            StatusCode::UNAUTHORIZED,
+            format!("Missing signature header '{}'", hmac.header),
+        ))?;
+    let signature = match &hmac.prefix {
+        Some(prefix) => signature.strip_prefix(prefix.as_str()).unwrap_or(signature),
+        None => signature,
+    };
+
+    if !verify_hmac_hex(&hmac.algorithm, hmac.secret.as_bytes(), body, signature) {
+        return Err((StatusCode::UNAUTHORIZED, "Invalid signature".to_string()));
+    }
+    Ok(())
+}
+
+pub(crate) fn verify_shared_secret(
+    headers: &HeaderMap,
+    secret: &SharedSecretConfig,
+) -> Result<(), (StatusCode, String)> {
+    match headers
+        .get(secret.header.as_str())
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(value) if constant_time_eq(value.trim().as_bytes(), secret.secret.as_bytes()) => {
+            Ok(())
+        }
+        Some(_) => Err((
+            StatusCode::UNAUTHORIZED,
+            "Invalid webhook secret".to_string(),
+        )),
+        None => Err((
+            StatusCode::UNAUTHORIZED,
+            format!("Missing secret header '{}'", secret.header),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    const BODY: &[u8] = br#"{"event":"message"}"#;
+
+    fn hmac_config(prefix: Option<&str>) -> HmacConfig {
+        HmacConfig {
+            secret: "s3cret".to_string(),
+            header: "X-Webhook-Hmac".to_string(),
+            algorithm: "sha256".to_string(),
+            prefix: prefix.map(str::to_string),
+        }
+    }
+
+    fn sign(body: &[u8]) -> String {
+        let mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        hex::encode(mac.chain_update(body).finalize().into_bytes())
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn hmac_accepts_a_valid_signature() {
+        let headers = headers("x-webhook-hmac", &sign(BODY));
+        assert!(verify_hmac(&headers, &hmac_config(None), BODY).is_ok());
+    }
+
+    #[test]
+    fn hmac_strips_the_prefix() {
+        let headers = headers("x-webhook-hmac", &format!("sha256={}", sign(BODY)));
+        assert!(verify_hmac(&headers, &hmac_config(Some("sha256=")), BODY).is_ok());
+    }
+
+    #[test]
+    fn hmac_rejects_a_tampered_body() {
+        let headers = headers("x-webhook-hmac", &sign(BODY));
+        let err = verify_hmac(&headers, &hmac_config(None), br#"{"event":"other"}"#).unwrap_err();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn hmac_rejects_a_missing_signature() {
+        let err = verify_hmac(&HeaderMap::new(), &hmac_config(None), BODY).unwrap_err();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+    }
+
+    fn secret_config() -> SharedSecretConfig {
+        SharedSecretConfig {
+            secret: "s3cret".to_string(),
+            header: "X-Webhook-Secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn shared_secret_accepts_the_secret() {
+        let headers = headers("x-webhook-secret", "s3cret");
+        assert!(verify_shared_secret(&headers, &secret_config()).is_ok());
+    }
+
+    #[test]
+    fn shared_secret_rejects_a_wrong_secret() {
+        let headers = headers("x-webhook-secret", "s3cret2");
+        let err = verify_shared_secret(&headers, &secret_config()).unwrap_err();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn shared_secret_rejects_a_missing_header() {
+        let err = verify_shared_secret(&HeaderMap::new(), &secret_config()).unwrap_err();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,11 +1,11 @@
 use axum::{
-    Json,
+    body::Bytes,
     extract::State,
     http::{HeaderMap, StatusCode},
 };
-use serde_json::Value as JsonValue;
 use tracing::info;
 
+use super::{parse_allowed_ids, parse_bool_header, verify_hmac, verify_shared_secret};
 use crate::{AppState, handlers, models::wacraft::WacraftWebhook};
 
 #[utoipa::path(
@@ -13,7 +13,9 @@ use crate::{AppState, handlers, models::wacraft::WacraftWebhook};
     path = "/webhooks/wacraft",
     tag = "webhooks",
     params(
-        ("x-allowed-wa-ids" = Option<String>, Header, description = "Comma-separated list of WhatsApp IDs to allow in dev mode.", example = "999999999999@c.us,111111111111@c.us"),
+        ("X-Webhook-Secret" = Option<String>, Header, description = "Shared secret; required when `WACRAFT_WEBHOOK_SECRET` is set (header name: `WACRAFT_WEBHOOK_SECRET_HEADER`)"),
+        ("X-Webhook-Hmac" = Option<String>, Header, description = "Hex HMAC of the raw body; required when `WACRAFT_WEBHOOK_HMAC_SECRET` is set (header name: `WACRAFT_WEBHOOK_HMAC_HEADER`)"),
+        ("x-allowed-wa-ids" = Option<String>, Header, description = "Comma-separated list of WhatsApp IDs (`*` wildcards allowed) to allow; replaces the configured allow lists. Block lists still apply.", example = "999999999999@c.us,111111111111@c.us"),
         ("x-typing" = Option<bool>, Header, description = "If `true`, typing indicators are allowed. Defaults to `false` for Wacraft as the API does not provide native support.", example = false),
         ("x-send-seen" = Option<bool>, Header, description = "If `true`, attempts to mark messages as read. Currently a best-effort operation.", example = false),
         ("x-ai-response" = Option<bool>, Header, description = "If `true`, AI-generated responses are sent back through Wacraft. Defaults to `true`.", example = true)
@@ -22,6 +24,7 @@ use crate::{AppState, handlers, models::wacraft::WacraftWebhook};
     responses(
         (status = 200, description = "Webhook accepted"),
         (status = 400, description = "Bad Request - Invalid webhook payload", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid secret or signature", body = crate::models::common::ErrorMessage),
         (status = 403, description = "Forbidden - WhatsApp ID not allowed", body = crate::models::common::ErrorMessage),
         (status = 500, description = "Handler error", body = crate::models::common::ErrorMessage)
     )
@@ -29,9 +32,17 @@ use crate::{AppState, handlers, models::wacraft::WacraftWebhook};
 pub async fn receive_wacraft(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<JsonValue>,
+    body: Bytes,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    let webhook: WacraftWebhook = serde_json::from_value(payload.clone()).map_err(|err| {
+    let auth = &state.cfg.webhook_auth;
+    if let Some(secret) = &auth.wacraft_secret {
+        verify_shared_secret(&headers, secret)?;
+    }
+    if let Some(hmac) = &auth.wacraft_hmac {
+        verify_hmac(&headers, hmac, &body)?;
+    }
+
+    let webhook: WacraftWebhook = serde_json::from_slice(&body).map_err(|err| {
         (
             StatusCode::BAD_REQUEST,
             format!("Failed to deserialize webhook payload: {err}"),
@@ -63,47 +74,3 @@ pub async fn receive_wacraft(
 
     Ok(StatusCode::OK)
 }
-
-fn parse_allowed_ids(headers: &HeaderMap) -> Result<Option<Vec<String>>, (StatusCode, String)> {
-    if let Some(ids_header) = headers.get("x-allowed-wa-ids") {
-        let ids_str = ids_header.to_str().map_err(|_| {
-            (
-                StatusCode::BAD_REQUEST,
-                "Header 'x-allowed-wa-ids' contains invalid characters.".to_string(),
-            )
-        })?;
-
-        let ids = ids_str
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect::<Vec<_>>();
-
-        if !ids.is_empty() {
-            return Ok(Some(ids));
-        }
-    }
-    Ok(None)
-}
-
-fn parse_bool_header(headers: &HeaderMap, key: &str) -> Result<Option<bool>, (StatusCode, String)> {
-    if let Some(value) = headers.get(key) {
-        let value = value.to_str().map_err(|_| {
-            (
-                StatusCode::BAD_REQUEST,
-                format!("Header '{}' contains invalid characters.", key),
-            )
-        })?;
-
-        match value {
-            "true" | "1" => Ok(Some(true)),
-            "false" | "0" => Ok(Some(false)),
-            _ => Err((
-                StatusCode::BAD_REQUEST,
-                format!("Header '{}' must be 'true' or 'false'.", key),
-            )),
-        }
-    } else {
-        Ok(None)
-    }
-}
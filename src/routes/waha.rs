@@ -1,11 +1,11 @@
 use axum::{
-    Json,
+    body::Bytes,
     extract::State,
     http::{HeaderMap, StatusCode},
 };
-use serde_json::Value as JsonValue;
 use tracing::info;
 
+use super::verify_hmac;
 use crate::{AppState, handlers, models::waha::WahaWebhook};
 
 #[utoipa::path(
@@ -13,7 +13,8 @@ use crate::{AppState, handlers, models::waha::WahaWebhook};
     path = "/webhooks/waha",
     tag = "webhooks",
     params(
-        ("x-allowed-wa-ids" = Option<String>, Header, description = "Comma-separated list of WhatsApp IDs to allow in dev mode.", example = "999999999999@c.us,111111111111@c.us"),
+        ("X-Webhook-Hmac" = Option<String>, Header, description = "Hex HMAC-SHA512 of the raw body; required when `WAHA_WEBHOOK_HMAC_SECRET` is set"),
+        ("x-allowed-wa-ids" = Option<String>, Header, description = "Comma-separated list of WhatsApp IDs (`*` wildcards allowed) to allow; replaces the configured allow lists. Block lists still apply.", example = "999999999999@c.us,111111111111@c.us"),
         ("x-typing" = Option<bool>, Header, description = "If `true`, typing indicators are allowed. Defaults to `true`.", example = true),
         ("x-send-seen" = Option<bool>, Header, description = "If `true`, sending read receipts (seen indicators) is allowed. Defaults to `true`.", example = true),
         ("x-ai-response" = Option<bool>, Header, description = "If `true`, AI-generated responses are allowed. Useful for development scenarios. Defaults to `true`.", example = true)
@@ -22,6 +23,7 @@ use crate::{AppState, handlers, models::waha::WahaWebhook};
     responses(
         (status = 200, description = "Webhook accepted"),
         (status = 400, description = "Bad Request - Invalid webhook payload", body = crate::models::common::ErrorMessage),
+        (status = 401, description = "Missing or invalid signature", body = crate::models::common::ErrorMessage),
         (status = 403, description = "Forbidden - WhatsApp ID not allowed", body = crate::models::common::ErrorMessage),
         (status = 500, description = "Handler error", body = crate::models::common::ErrorMessage)
     )
@@ -29,10 +31,14 @@ use crate::{AppState, handlers, models::waha::WahaWebhook};
 pub async fn receive_waha(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<JsonValue>,
+    body: Bytes,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(hmac) = &state.cfg.webhook_auth.waha_hmac {
+        verify_hmac(&headers, hmac, &body)?;
+    }
+
     // Attempt to parse into our WAHA model; if it fails, return an error
-    let webhook: WahaWebhook = serde_json::from_value(payload.clone()).map_err(|err| {
+    let webhook: WahaWebhook = serde_json::from_slice(&body).map_err(|err| {
         // If deserialization fails, return an internal server error with the error message
         (
             StatusCode::BAD_REQUEST,
@@ -77,7 +83,7 @@ pub async fn receive_waha(
         }
     }
 
-    // In dev mode, the x-allowed-wa-ids header is required
+    // The x-allowed-wa-ids header overrides the configured allow lists
     if let Some(ids_header) = headers.get("x-allowed-wa-ids") {
         if let Ok(ids_str) = ids_header.to_str() {
             // Parse the comma-separated string into a Vec<String>
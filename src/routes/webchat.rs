@@ -0,0 +1,122 @@
+use axum::{
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::Response,
+};
+use serde::Deserialize;
+use tracing::{debug, error, info};
+
+use crate::{
+    AppState, handlers,
+    models::webchat::{WebChatFrame, WebChatInbound},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct WebChatParams {
+    /// Session token from a previous `session` frame; omitted or unknown starts a new session.
+    pub session: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/ws/chat",
+    tag = "chat",
+    params(
+        ("session" = Option<String>, Query, description = "Session token to resume; buffered replies are delivered on reconnect.")
+    ),
+    responses(
+        (status = 101, description = "WebSocket upgrade. Client frames: `WebChatInbound`; server frames: `WebChatFrame`."),
+        (status = 503, description = "Web chat disabled", body = crate::models::common::ErrorMessage)
+    )
+)]
+pub async fn ws_chat(
+    State(state): State<AppState>,
+    Query(params): Query<WebChatParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    if !state.cfg.webchat.enabled {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Web chat is disabled.".to_string(),
+        ));
+    }
+
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, params.session)))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState, session: Option<String>) {
+    let mut attachment = state.webchat.attach(session.as_deref());
+    let session = attachment.session.clone();
+    info!("Web-chat socket attached (session={})", session);
+
+    if send_frame(
+        &mut socket,
+        &WebChatFrame::Session {
+            session: session.clone(),
+        },
+    )
+    .await
+    .is_err()
+    {
+        state.webchat.detach(&session, attachment.connection_id);
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            frame = attachment.rx.recv() => {
+                // `None` means another socket took over this session.
+                let Some(frame) = frame else { break };
+                if send_frame(&mut socket, &frame).await.is_err() {
+                    // Not delivered: keep it for the next reconnect.
+                    state.webchat.detach(&session, attachment.connection_id);
+                    state.webchat.send(&session, frame);
+                    return;
+                }
+            }
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => {
+                        debug!("Web-chat socket error (session={}): {}", session, err);
+                        break;
+                    }
+                };
+
+                match serde_json::from_str::<WebChatInbound>(text.as_str()) {
+                    Ok(WebChatInbound::Message { text }) if !text.trim().is_empty() => {
+                        // Handled off the socket loop so replies and new frames keep flowing;
+                        // the per-chat lock keeps turns in order.
+                        let state = state.clone();
+                        let session = session.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = handlers::webchat::handle_text(&state, &session, &text).await {
+                                error!("Web-chat turn failed (session={}): {}", session, err);
+                                state.webchat.send(&session, WebChatFrame::Error { error: err.to_string() });
+                            }
+                        });
+                    }
+                    Ok(WebChatInbound::Message { .. }) => {}
+                    Ok(WebChatInbound::Ping) => state.webchat.send(&session, WebChatFrame::Pong),
+                    Err(err) => state.webchat.send(
+                        &session,
+                        WebChatFrame::Error { error: format!("invalid frame: {err}") },
+                    ),
+                }
+            }
+        }
+    }
+
+    state.webchat.detach(&session, attachment.connection_id);
+    info!("Web-chat socket detached (session={})", session);
+}
+
+async fn send_frame(socket: &mut WebSocket, frame: &WebChatFrame) -> Result<(), axum::Error> {
+    let payload = serde_json::to_string(frame).unwrap_or_default();
+    socket.send(Message::Text(payload.into())).await
+}
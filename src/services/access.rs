@@ -0,0 +1,135 @@
+use crate::{
+    config::{AccessListConfig, ControlStoreConfig},
+    models::access::{AccessListKind, AccessRule, AccessRules},
+    store::{
+        access::{AccessStore, build_access_store},
+        blocking,
+    },
+};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Allow and block lists per provider and session: the ones from `ACCESS_LISTS_PATH` plus
+/// the ones added through the admin API.
+pub struct AccessControl {
+    config: Vec<AccessRule>,
+    store: Arc<dyn AccessStore>,
+}
+
+impl AccessControl {
+    pub fn new(lists: &[AccessListConfig], store: &ControlStoreConfig) -> Result<Self, String> {
+        let rule = |list: &AccessListConfig, kind: AccessListKind, pattern: &String| AccessRule {
+            provider: list.provider.clone(),
+            session: list.session.clone(),
+            list: kind,
+            pattern: pattern.trim().to_string(),
+        };
+        let config = lists
+            .iter()
+            .flat_map(|list| {
+                let allow = list
+                    .allow
+                    .iter()
+                    .map(move |p| rule(list, AccessListKind::Allow, p));
+                let deny = list
+                    .deny
+                    .iter()
+                    .map(move |p| rule(list, AccessListKind::Deny, p));
+                allow.chain(deny)
+            })
+            .collect();
+        Ok(Self {
+            config,
+            store: build_access_store(store)?,
+        })
+    }
+
+    /// Whether the chat may get an AI reply. Block lists always apply; `allowed_ids` (the
+    /// `x-allowed-wa-ids` header) replaces the allow lists when given. Store errors leave
+    /// only the config lists in force.
+    pub async fn is_allowed(
+        &self,
+        provider: &str,
+        session: Option<&str>,
+        chat_id: &str,
+        allowed_ids: Option<&[String]>,
+    ) -> bool {
+        let store = Arc::clone(&self.store);
+        let admin = blocking(move || store.list()).await.unwrap_or_else(|err| {
+            warn!("Failed to read access rules: {}", err);
+            Vec::new()
+        });
+        let rules: Vec<&AccessRule> = self
+            .config
+            .iter()
+            .chain(&admin)
+            .filter(|rule| {
+                rule.provider == provider
+                    && rule.session.as_deref().is_none_or(|s| Some(s) == session)
+            })
+            .collect();
+
+        if let Some(rule) = rules.iter().find(|rule| {
+            rule.list == AccessListKind::Deny && pattern_matches(&rule.pattern, chat_id)
+        }) {
+            warn!(
+                "Blocking message from '{}': on the block list ({})",
+                chat_id, rule.pattern
+            );
+            return false;
+        }
+
+        let allowed = match allowed_ids {
+            Some(ids) => ids.iter().any(|id| pattern_matches(id, chat_id)),
+            None => {
+                let mut allow = rules
+                    .iter()
+                    .filter(|rule| rule.list == AccessListKind::Allow)
+                    .peekable();
+                allow.peek().is_none() || allow.any(|rule| pattern_matches(&rule.pattern, chat_id))
+            }
+        };
+        if !allowed {
+            warn!("Blocking message from '{}': not on the allow list", chat_id);
+        }
+        allowed
+    }
+
+    pub fn rules(&self) -> Result<AccessRules, String> {
+        Ok(AccessRules {
+            config: self.config.clone(),
+            admin: self.store.list()?,
+        })
+    }
+
+    /// Returns `false` when the rule already existed.
+    pub fn add(&self, rule: &AccessRule) -> Result<bool, String> {
+        self.store.add(rule)
+    }
+
+    /// Returns `false` when there was no such admin rule. Config rules can't be removed.
+    pub fn remove(&self, rule: &AccessRule) -> Result<bool, String> {
+        self.store.remove(rule)
+    }
+}
+
+/// `*` matches any run of characters; everything else matches itself.
+pub fn pattern_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
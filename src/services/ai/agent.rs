@@ -0,0 +1,230 @@
+use super::{AiBackend, AiError};
+use crate::{
+    config::AgentBackendConfig,
+    models::ai::{AiJobAccepted, InputRequest, LlmApiResponse},
+};
+use async_trait::async_trait;
+use reqwest::{StatusCode, header};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Our own agent protocol: the `InputRequest` is posted as-is and the agent keeps the
+/// conversation memory by `thread_id`.
+pub struct AgentBackend {
+    http: reqwest::Client,
+    config: AgentBackendConfig,
+    /// Set once the stream endpoint turned out not to exist, so we stop trying it.
+    stream_unavailable: AtomicBool,
+}
+
+impl AgentBackend {
+    pub fn new(config: AgentBackendConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            config,
+            stream_unavailable: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl AiBackend for AgentBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let url = self
+            .config
+            .base_url
+            .join(&self.config.messages_user_path)
+            .map_err(|e| e.to_string())?;
+        let res = self
+            .http
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .map_err(AiError::Request)?;
+        if res.status() == StatusCode::ACCEPTED {
+            return deferred(res).await;
+        }
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        res.json::<LlmApiResponse>()
+            .await
+            .map_err(|e| AiError::Decode(e.to_string()))
+    }
+
+    /// Posted to the user messages endpoint like a turn (the `chat_interface` tells the agent
+    /// not to answer); whatever comes back is ignored.
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        let url = self
+            .config
+            .base_url
+            .join(&self.config.messages_user_path)
+            .map_err(|e| e.to_string())?;
+        let res = self
+            .http
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .map_err(AiError::Request)?;
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        Ok(())
+    }
+
+    /// Posts to `AI_STREAM_PATH` and reads SSE (`data: ...`) or NDJSON lines. Each line is
+    /// a text delta, either raw or as `{"delta": "..."}`; a line that parses as an
+    /// `LlmApiResponse` carries the final `next_step` (its `response` defaults to the
+    /// streamed text). Falls back to the single-shot endpoint when there is no stream path,
+    /// the endpoint doesn't exist, or the agent answers with plain JSON.
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let Some(stream_path) = &self.config.stream_path else {
+            return self.send_user_message(body).await;
+        };
+        if self.stream_unavailable.load(Ordering::Relaxed) {
+            return self.send_user_message(body).await;
+        }
+
+        let url = self
+            .config
+            .base_url
+            .join(stream_path)
+            .map_err(|e| e.to_string())?;
+        let mut res = self
+            .http
+            .post(url)
+            .header(header::ACCEPT, "text/event-stream, application/x-ndjson")
+            .json(body)
+            .send()
+            .await
+            .map_err(AiError::Request)?;
+
+        let status = res.status();
+        if matches!(
+            status,
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            warn!(
+                "Agent stream endpoint answered {}, using single-shot calls from now on",
+                status
+            );
+            self.stream_unavailable.store(true, Ordering::Relaxed);
+            return self.send_user_message(body).await;
+        }
+        if status == StatusCode::ACCEPTED {
+            return deferred(res).await;
+        }
+        if !status.is_success() {
+            return Err(AiError::Status(status));
+        }
+
+        let is_json = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        if is_json {
+            return res
+                .json::<LlmApiResponse>()
+                .await
+                .map_err(|e| AiError::Decode(e.to_string()));
+        }
+
+        let mut text = String::new();
+        let mut last: Option<LlmApiResponse> = None;
+        let mut pending: Vec<u8> = Vec::new();
+        let mut handle_line = |line: &[u8], text: &mut String| -> Option<String> {
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim_end_matches('\r');
+            let payload = match line.strip_prefix("data:") {
+                Some(data) => data.strip_prefix(' ').unwrap_or(data),
+                // SSE comments, `event:`/`id:`/`retry:` fields and keep-alive blank lines
+                None if line.is_empty()
+                    || line.starts_with(':')
+                    || line.starts_with("event:")
+                    || line.starts_with("id:")
+                    || line.starts_with("retry:") =>
+                {
+                    return None;
+                }
+                None => line,
+            };
+            if payload.is_empty() || payload == "[DONE]" {
+                return None;
+            }
+            let delta = match serde_json::from_str::<Value>(payload) {
+                Ok(Value::String(delta)) => delta,
+                Ok(Value::Object(obj)) if obj.contains_key("next_step") => {
+                    match serde_json::from_value::<LlmApiResponse>(Value::Object(obj)) {
+                        Ok(res) => last = Some(res),
+                        Err(err) => warn!("Ignoring malformed final stream event: {}", err),
+                    }
+                    return None;
+                }
+                Ok(Value::Object(obj)) => obj.get("delta")?.as_str()?.to_string(),
+                _ => payload.to_string(),
+            };
+            text.push_str(&delta);
+            Some(delta)
+        };
+
+        while let Some(bytes) = res.chunk().await.map_err(AiError::Request)? {
+            pending.extend_from_slice(&bytes);
+            while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=pos).collect();
+                if let Some(delta) = handle_line(&line[..pos], &mut text) {
+                    // The caller may have stopped listening; keep reading the whole reply.
+                    let _ = deltas.send(delta).await;
+                }
+            }
+        }
+        if let Some(delta) = handle_line(&pending, &mut text) {
+            let _ = deltas.send(delta).await;
+        }
+
+        let streamed = (!text.is_empty()).then_some(text);
+        Ok(match last {
+            Some(mut res) => {
+                if res.response.is_none() {
+                    res.response = streamed;
+                }
+                res
+            }
+            None => LlmApiResponse {
+                next_step: "end".to_string(),
+                next_step_reason: "stream finished".to_string(),
+                response: streamed,
+                parts: Vec::new(),
+                job_id: None,
+                schedule: Vec::new(),
+                handoff: false,
+            },
+        })
+    }
+}
+
+/// `202 {job_id}`: the agent works on the turn in the background and posts the reply to
+/// `/v1/callbacks/ai/{job_id}` later.
+async fn deferred(res: reqwest::Response) -> Result<LlmApiResponse, AiError> {
+    let accepted = res
+        .json::<AiJobAccepted>()
+        .await
+        .map_err(|e| AiError::Decode(e.to_string()))?;
+    Ok(LlmApiResponse {
+        next_step: "deferred".to_string(),
+        next_step_reason: "job accepted".to_string(),
+        response: None,
+        parts: Vec::new(),
+        job_id: Some(accepted.job_id),
+        schedule: Vec::new(),
+        handoff: false,
+    })
+}
@@ -0,0 +1,123 @@
+use super::{AiBackend, AiError, user_text};
+use crate::{
+    config::DifyConfig,
+    models::{
+        ai::{InputRequest, LlmApiResponse},
+        dify::{DifyChatRequest, DifyChatResponse},
+    },
+};
+use async_trait::async_trait;
+use reqwest::{StatusCode, Url};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Dify chat app (`/chat-messages`). Dify assigns its own `conversation_id`, so the adapter
+/// remembers which one belongs to each `thread_id` (in memory; a restart starts new
+/// conversations).
+pub struct DifyBackend {
+    http: reqwest::Client,
+    config: DifyConfig,
+    conversations: Mutex<HashMap<String, String>>,
+}
+
+impl DifyBackend {
+    pub fn new(config: DifyConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            config,
+            conversations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn endpoint(&self) -> Result<Url, String> {
+        let mut url = self.config.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "Dify base URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .push("chat-messages");
+        Ok(url)
+    }
+
+    fn conversation_id(&self, thread_id: &str) -> String {
+        let conversations = self
+            .conversations
+            .lock()
+            .expect("dify conversations poisoned");
+        conversations.get(thread_id).cloned().unwrap_or_default()
+    }
+
+    fn set_conversation_id(&self, thread_id: &str, conversation_id: Option<String>) {
+        let mut conversations = self
+            .conversations
+            .lock()
+            .expect("dify conversations poisoned");
+        match conversation_id {
+            Some(id) => conversations.insert(thread_id.to_string(), id),
+            None => conversations.remove(thread_id),
+        };
+    }
+
+    async fn post(
+        &self,
+        query: &str,
+        conversation_id: &str,
+        user: &str,
+    ) -> Result<reqwest::Response, AiError> {
+        let request = DifyChatRequest {
+            inputs: json!({}),
+            query,
+            response_mode: "blocking",
+            conversation_id,
+            user,
+        };
+        self.http
+            .post(self.endpoint()?)
+            .bearer_auth(&self.config.api_key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(AiError::Request)
+    }
+}
+
+#[async_trait]
+impl AiBackend for DifyBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let query = user_text(&body.data);
+        let conversation_id = self.conversation_id(&body.thread_id);
+
+        let mut res = self.post(&query, &conversation_id, &body.thread_id).await?;
+        // The conversation may have been deleted on the Dify side; start a fresh one.
+        if res.status() == StatusCode::NOT_FOUND && !conversation_id.is_empty() {
+            debug!(
+                "Dify conversation {} is gone, starting a new one",
+                conversation_id
+            );
+            self.set_conversation_id(&body.thread_id, None);
+            res = self.post(&query, "", &body.thread_id).await?;
+        }
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        let answer = res
+            .json::<DifyChatResponse>()
+            .await
+            .map_err(|e| AiError::Decode(e.to_string()))?;
+
+        if answer.conversation_id.is_some() {
+            self.set_conversation_id(&body.thread_id, answer.conversation_id);
+        }
+
+        Ok(LlmApiResponse {
+            next_step: "end".to_string(),
+            next_step_reason: "dify".to_string(),
+            response: Some(answer.answer).filter(|a| !a.trim().is_empty()),
+            parts: Vec::new(),
+            job_id: None,
+            schedule: Vec::new(),
+            handoff: false,
+        })
+    }
+}
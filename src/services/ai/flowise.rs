@@ -0,0 +1,68 @@
+use super::{AiBackend, AiError, user_text};
+use crate::{
+    config::FlowiseConfig,
+    models::{
+        ai::{InputRequest, LlmApiResponse},
+        flowise::{FlowiseOverrideConfig, FlowisePredictionRequest, FlowisePredictionResponse},
+    },
+};
+use async_trait::async_trait;
+use reqwest::Url;
+
+/// Flowise chatflow (`/api/v1/prediction/{id}`). Our `thread_id` is passed as the
+/// `sessionId`, so Flowise's memory nodes keep one conversation per chat.
+pub struct FlowiseBackend {
+    http: reqwest::Client,
+    config: FlowiseConfig,
+}
+
+impl FlowiseBackend {
+    pub fn new(config: FlowiseConfig, http: reqwest::Client) -> Self {
+        Self { http, config }
+    }
+
+    fn endpoint(&self) -> Result<Url, String> {
+        let mut url = self.config.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "Flowise base URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .extend(["api", "v1", "prediction", &self.config.chatflow_id]);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl AiBackend for FlowiseBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let question = user_text(&body.data);
+        let request = FlowisePredictionRequest {
+            question: &question,
+            override_config: FlowiseOverrideConfig {
+                session_id: &body.thread_id,
+            },
+        };
+
+        let mut req = self.http.post(self.endpoint()?).json(&request);
+        if let Some(key) = &self.config.api_key {
+            req = req.bearer_auth(key);
+        }
+        let res = req.send().await.map_err(AiError::Request)?;
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        let prediction = res
+            .json::<FlowisePredictionResponse>()
+            .await
+            .map_err(|e| AiError::Decode(e.to_string()))?;
+
+        Ok(LlmApiResponse {
+            next_step: "end".to_string(),
+            next_step_reason: "flowise".to_string(),
+            response: prediction.text.filter(|t| !t.trim().is_empty()),
+            parts: Vec::new(),
+            job_id: None,
+            schedule: Vec::new(),
+            handoff: false,
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use super::{AiBackend, AiError, user_text};
+use crate::{
+    config::HistoryConfig,
+    models::ai::{HistoryTurn, InputRequest, LlmApiResponse},
+    store::{blocking, history::HistoryStore},
+};
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation so far in a few sentences. Keep names, \
+     facts, decisions and open questions. Reply with the summary only.";
+
+/// Wraps a backend with adapter-side memory: earlier turns of the thread are loaded into
+/// `InputRequest.history`, and the new user/assistant turns are stored afterwards.
+pub struct HistoryBackend {
+    inner: Arc<dyn AiBackend>,
+    store: Arc<dyn HistoryStore>,
+    max_turns: usize,
+    token_budget: Option<usize>,
+    /// Threads with a summary in progress
+    summarizing: Arc<Mutex<HashSet<String>>>,
+}
+
+impl HistoryBackend {
+    pub fn new(
+        inner: Arc<dyn AiBackend>,
+        store: Arc<dyn HistoryStore>,
+        cfg: &HistoryConfig,
+    ) -> Self {
+        Self {
+            inner,
+            store,
+            max_turns: cfg.max_turns,
+            token_budget: cfg.token_budget,
+            summarizing: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Newest turns that fit in `max_turns` and the token budget; summaries always go first.
+    fn context(&self, turns: Vec<HistoryTurn>) -> Vec<HistoryTurn> {
+        let (summaries, messages): (Vec<_>, Vec<_>) =
+            turns.into_iter().partition(|t| t.role == "system");
+
+        let mut budget = self
+            .token_budget
+            .map(|b| b.saturating_sub(summaries.iter().map(|t| estimate_tokens(&t.content)).sum()));
+        let mut picked = Vec::new();
+        for turn in messages.into_iter().rev().take(self.max_turns) {
+            if let Some(left) = budget.as_mut() {
+                let cost = estimate_tokens(&turn.content);
+                if cost > *left {
+                    break;
+                }
+                *left -= cost;
+            }
+            picked.push(turn);
+        }
+        picked.reverse();
+
+        summaries.into_iter().chain(picked).collect()
+    }
+
+    /// Summarizes the thread in the background, so the turn never waits for the extra AI
+    /// call. One summary runs per thread at a time.
+    fn spawn_summary(&self, body: &InputRequest) {
+        if body.summarize_message_window == 0
+            || !self
+                .summarizing
+                .lock()
+                .expect("summary guard poisoned")
+                .insert(body.thread_id.clone())
+        {
+            return;
+        }
+        let inner = Arc::clone(&self.inner);
+        let store = Arc::clone(&self.store);
+        let summarizing = Arc::clone(&self.summarizing);
+        let body = body.clone();
+        tokio::spawn(async move {
+            maybe_summarize(inner.as_ref(), &store, &body).await;
+            summarizing
+                .lock()
+                .expect("summary guard poisoned")
+                .remove(&body.thread_id);
+        });
+    }
+
+    /// Replays the stored context into the call; the new turns are stored once the reply is
+    /// complete, streamed or not.
+    async fn answer(
+        &self,
+        body: &InputRequest,
+        deltas: Option<mpsc::Sender<String>>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let past = load(&self.store, &body.thread_id)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Failed to load history for {}: {}", body.thread_id, err);
+                Vec::new()
+            });
+
+        let mut req = body.clone();
+        req.history = Some(self.context(past));
+        let res = match deltas {
+            Some(deltas) => self.inner.stream_user_message(&req, deltas).await?,
+            None => self.inner.send_user_message(&req).await?,
+        };
+
+        let mut turns = vec![HistoryTurn::new("user", user_text(&body.data))];
+        if let Some(reply) = &res.response {
+            turns.push(HistoryTurn::new("assistant", reply.clone()));
+        }
+        append(&self.store, &body.thread_id, turns).await;
+
+        self.spawn_summary(body);
+        Ok(res)
+    }
+}
+
+/// Once more than `summarize_message_keep + summarize_message_window` messages are stored,
+/// everything but the newest `summarize_message_keep` is folded into one `system` summary.
+/// Earlier summaries are folded in too when `summarize_system_messages` is set, otherwise
+/// they are kept as they are.
+async fn maybe_summarize(
+    inner: &dyn AiBackend,
+    store: &Arc<dyn HistoryStore>,
+    body: &InputRequest,
+) {
+    let keep = body.summarize_message_keep as usize;
+    let window = body.summarize_message_window as usize;
+    if window == 0 {
+        return;
+    }
+
+    let turns = match load(store, &body.thread_id).await {
+        Ok(turns) => turns,
+        Err(err) => {
+            warn!("Failed to load history for {}: {}", body.thread_id, err);
+            return;
+        }
+    };
+    let messages = turns.iter().filter(|t| t.role != "system").count();
+    if messages <= keep + window {
+        return;
+    }
+
+    // Split right before the `keep`-th newest non-system message.
+    let mut seen = 0;
+    let split = turns
+        .iter()
+        .rposition(|t| {
+            if t.role != "system" {
+                seen += 1;
+            }
+            seen == keep + 1
+        })
+        .map_or(0, |idx| idx + 1);
+    let old = &turns[..split];
+
+    let (to_summarize, carried): (Vec<_>, Vec<_>) = old
+        .iter()
+        .cloned()
+        .partition(|t| body.summarize_system_messages || t.role != "system");
+
+    let mut req = body.clone();
+    // Separate thread so agents with their own memory don't mix this into the conversation.
+    req.thread_id = format!("{}#summary", body.thread_id);
+    req.data = json!({ "text": SUMMARY_PROMPT, "source": "history_summary" });
+    req.history = Some(to_summarize);
+
+    let summary = match inner.send_user_message(&req).await {
+        Ok(LlmApiResponse {
+            response: Some(text),
+            ..
+        }) if !text.trim().is_empty() => text,
+        Ok(_) => {
+            debug!("Summary for {} came back empty", body.thread_id);
+            return;
+        }
+        Err(err) => {
+            warn!(
+                "Failed to summarize history for {}: {}",
+                body.thread_id, err
+            );
+            return;
+        }
+    };
+
+    // Turns stored while the summary was made come after `old` and are kept.
+    let mut compacted = carried;
+    compacted.push(HistoryTurn::new(
+        "system",
+        format!("Summary of the earlier conversation: {summary}"),
+    ));
+    let (store, thread_id, count) = (Arc::clone(store), body.thread_id.clone(), old.len());
+    let replaced = blocking(move || store.replace_oldest(&thread_id, count, &compacted)).await;
+    if let Err(err) = replaced {
+        warn!(
+            "Failed to store history summary for {}: {}",
+            body.thread_id, err
+        );
+    }
+}
+
+#[async_trait]
+impl AiBackend for HistoryBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        self.answer(body, None).await
+    }
+
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        self.answer(body, Some(deltas)).await
+    }
+
+    /// The deferred reply is stored now; the user turn was stored with the `202`.
+    async fn finish_deferred(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        self.inner.finish_deferred(thread_id, res).await;
+        if let Some(reply) = &res.response {
+            let turn = HistoryTurn::new("assistant", reply.clone());
+            append(&self.store, thread_id, vec![turn]).await;
+        }
+    }
+
+    /// Stored as an assistant turn, so the model sees what the operator told the user.
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        let turn = HistoryTurn::new("assistant", user_text(&body.data));
+        append(&self.store, &body.thread_id, vec![turn]).await;
+        self.inner.add_context(body).await
+    }
+}
+
+async fn load(store: &Arc<dyn HistoryStore>, thread_id: &str) -> Result<Vec<HistoryTurn>, String> {
+    let (store, thread_id) = (Arc::clone(store), thread_id.to_string());
+    blocking(move || store.load(&thread_id)).await
+}
+
+/// Store errors are logged; they never fail the turn.
+async fn append(store: &Arc<dyn HistoryStore>, thread_id: &str, turns: Vec<HistoryTurn>) {
+    let (store, id) = (Arc::clone(store), thread_id.to_string());
+    if let Err(err) = blocking(move || store.append(&id, &turns)).await {
+        warn!("Failed to store history for {}: {}", thread_id, err);
+    }
+}
+
+/// Rough estimate (~4 characters per token); good enough for budgeting.
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
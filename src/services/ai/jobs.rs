@@ -0,0 +1,122 @@
+use super::AiError;
+use crate::models::ai::LlmApiResponse;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Sends a deferred reply to the chat it belongs to. Gets `Err(AiError::JobTimeout)` when
+/// the callback never came.
+pub type Delivery = Box<
+    dyn FnOnce(Result<LlmApiResponse, AiError>) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send,
+>;
+
+enum Job {
+    /// The turn is waiting for its callback.
+    Pending {
+        chat_id: String,
+        deadline: Instant,
+        delivery: Delivery,
+    },
+    /// The callback beat the registration (the agent answered very fast).
+    Early {
+        res: LlmApiResponse,
+        received_at: Instant,
+    },
+}
+
+/// Turns the agent accepted with `202 {job_id}`, waiting for `POST /v1/callbacks/ai/{job_id}`.
+/// Jobs live in memory only: a restart drops them.
+pub struct AiJobs {
+    timeout: Duration,
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl AiJobs {
+    pub fn new(timeout: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            timeout,
+            jobs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Registers a deferred turn. `delivery` runs with the callback's response, or with a
+    /// timeout error once `AI_JOB_TIMEOUT_SECS` have passed.
+    pub fn defer(self: &Arc<Self>, job_id: &str, chat_id: &str, delivery: Delivery) {
+        let mut jobs = self.jobs.lock().expect("job registry poisoned");
+        if let Some(Job::Early { res, .. }) = jobs.remove(job_id) {
+            drop(jobs);
+            tokio::spawn(delivery(Ok(res)));
+            return;
+        }
+        debug!("Waiting for AI job {} (chat {})", job_id, chat_id);
+        jobs.insert(
+            job_id.to_string(),
+            Job::Pending {
+                chat_id: chat_id.to_string(),
+                deadline: Instant::now() + self.timeout,
+                delivery,
+            },
+        );
+        drop(jobs);
+
+        let registry = Arc::clone(self);
+        let job_id = job_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(registry.timeout).await;
+            let job = {
+                let mut jobs = registry.jobs.lock().expect("job registry poisoned");
+                // The id may have been reused by a newer turn with a later deadline.
+                match jobs.remove(&job_id) {
+                    Some(Job::Pending {
+                        chat_id,
+                        deadline,
+                        delivery,
+                    }) if deadline <= Instant::now() => Some((chat_id, delivery)),
+                    Some(job) => {
+                        jobs.insert(job_id.clone(), job);
+                        None
+                    }
+                    None => None,
+                }
+            };
+            if let Some((chat_id, delivery)) = job {
+                warn!("AI job {} for chat {} timed out", job_id, chat_id);
+                delivery(Err(AiError::JobTimeout(job_id))).await;
+            }
+        });
+    }
+
+    /// Hands the callback's response to the waiting turn. Returns `false` when no turn was
+    /// waiting yet; the response is then kept until the turn registers (or expires).
+    pub fn complete(&self, job_id: &str, res: LlmApiResponse) -> bool {
+        let mut jobs = self.jobs.lock().expect("job registry poisoned");
+        let timeout = self.timeout;
+        jobs.retain(|_, job| match job {
+            Job::Early { received_at, .. } => received_at.elapsed() < timeout,
+            Job::Pending { .. } => true,
+        });
+
+        match jobs.remove(job_id) {
+            Some(Job::Pending {
+                chat_id, delivery, ..
+            }) => {
+                debug!("AI job {} finished, replying to {}", job_id, chat_id);
+                tokio::spawn(delivery(Ok(res)));
+                true
+            }
+            Some(Job::Early { .. }) | None => {
+                jobs.insert(
+                    job_id.to_string(),
+                    Job::Early {
+                        res,
+                        received_at: Instant::now(),
+                    },
+                );
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{
+    config::{AiBackendConfig, AiResilienceConfig, Config},
+    models::ai::{InputRequest, LlmApiResponse},
+    services::{moderation::Moderator, redaction::Redactor},
+    store::history::{HistoryStore, build_history_store},
+};
+use async_trait::async_trait;
+use moderate::Stage;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::mpsc;
+
+pub mod agent;
+pub mod dify;
+pub mod flowise;
+pub mod history;
+pub mod jobs;
+pub mod moderate;
+pub mod openai;
+pub mod rasa;
+pub mod redact;
+pub mod resilience;
+pub mod router;
+pub mod shadow;
+pub mod split;
+pub mod stream;
+
+#[derive(Debug, Error)]
+pub enum AiError {
+    #[error("request error: {0}")]
+    Request(reqwest::Error),
+    #[error("ai status {0}")]
+    Status(reqwest::StatusCode),
+    #[error("json error: {0}")]
+    Decode(String),
+    #[error("circuit open for {0}")]
+    CircuitOpen(String),
+    #[error("no callback for ai job {0}")]
+    JobTimeout(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl AiError {
+    /// Connection failures (connect timeouts included) and 5xx are worth another try; 4xx
+    /// and bad bodies are not. Neither are read timeouts: the backend may already be working
+    /// on the turn, and posting it again would run it twice.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AiError::Request(err) => err.is_connect(),
+            AiError::Status(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// What counts towards the circuit breaker: everything worth a retry, plus timeouts (an
+    /// agent that accepts connections but never answers) and open circuits further in.
+    pub fn is_unhealthy(&self) -> bool {
+        match self {
+            AiError::Request(err) => err.is_connect() || err.is_timeout(),
+            AiError::CircuitOpen(_) => true,
+            _ => self.is_retryable(),
+        }
+    }
+}
+
+impl From<String> for AiError {
+    fn from(err: String) -> Self {
+        AiError::Other(err)
+    }
+}
+
+/// Something that can answer a user turn. Handlers only ever talk to this trait, so the
+/// backend is a config choice (`AI_BACKEND`).
+#[async_trait]
+pub trait AiBackend: Send + Sync {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError>;
+
+    /// Like `send_user_message`, but pushes the reply text to `deltas` while it is generated;
+    /// the returned response still holds the whole reply. Backends that can't stream answer
+    /// in one piece and push nothing.
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        drop(deltas);
+        self.send_user_message(body).await
+    }
+
+    /// Tells the backend about a message a human operator sent in the thread (`data.text`),
+    /// without asking for a reply. Backends that only understand user turns ignore it.
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        let _ = body;
+        Ok(())
+    }
+
+    /// Called with the response of a turn the backend deferred (`202 {job_id}`) once its
+    /// callback arrived, so wrappers can treat it like the reply of their own call.
+    async fn finish_deferred(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        let _ = (thread_id, res);
+    }
+}
+
+/// Builds the default backend and every routed one, each wrapped with the history store
+/// when `HISTORY_STORE` is set (one store shared by all; threads are keyed by `thread_id`).
+/// With a `moderator`, replies are checked inside the history wrapper, so the stored reply
+/// is the one the user sees, and user messages outside it, so blocked ones are never
+/// stored. PII redaction wraps everything when `redactor` is given, so stored history
+/// and the moderation endpoint only ever see tokens. AI calls get their own HTTP client so
+/// their timeouts don't apply to provider calls.
+pub fn build_router(
+    cfg: &Config,
+    moderator: Option<Arc<Moderator>>,
+    redactor: Option<Arc<Redactor>>,
+) -> Result<router::AiRouter, String> {
+    let http = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(cfg.ai_resilience.connect_timeout_ms))
+        .read_timeout(Duration::from_millis(cfg.ai_resilience.read_timeout_ms))
+        .build()
+        .map_err(|err| format!("Failed to build the AI HTTP client: {err}"))?;
+    let store = cfg
+        .history
+        .store
+        .as_ref()
+        .map(build_history_store)
+        .transpose()?;
+    let with_history = |backend: Arc<dyn AiBackend>| -> Arc<dyn AiBackend> {
+        match &store {
+            Some(store) => Arc::new(history::HistoryBackend::new(
+                backend,
+                Arc::clone(store) as Arc<dyn HistoryStore>,
+                &cfg.history,
+            )),
+            None => backend,
+        }
+    };
+    let moderated = |backend: Arc<dyn AiBackend>, stage: Stage| -> Arc<dyn AiBackend> {
+        match &moderator {
+            Some(moderator) if stage == Stage::Input || moderator.checks_replies() => Arc::new(
+                moderate::ModeratingBackend::new(backend, Arc::clone(moderator), stage),
+            ),
+            _ => backend,
+        }
+    };
+    let wrap = |backend: Arc<dyn AiBackend>| -> Arc<dyn AiBackend> {
+        let backend = moderated(backend, Stage::Reply);
+        let backend = with_history(backend);
+        let backend = moderated(backend, Stage::Input);
+        match &redactor {
+            Some(redactor) => {
+                Arc::new(redact::RedactingBackend::new(backend, Arc::clone(redactor)))
+            }
+            None => backend,
+        }
+    };
+
+    let default = wrap(build_backend(
+        &cfg.ai_backend,
+        &cfg.ai_resilience,
+        http.clone(),
+    ));
+    let routes = cfg
+        .ai_routes
+        .iter()
+        .map(|route| {
+            let backend = route
+                .backend
+                .as_ref()
+                .map(|backend| wrap(build_backend(backend, &cfg.ai_resilience, http.clone())));
+            (route.clone(), backend)
+        })
+        .collect();
+
+    Ok(router::AiRouter::new(default, routes))
+}
+
+/// Every backend that calls out over HTTP gets its own retries and circuit breaker;
+/// `split` and `shadow` just compose those.
+pub fn build_backend(
+    config: &AiBackendConfig,
+    resilience: &AiResilienceConfig,
+    http: reqwest::Client,
+) -> Arc<dyn AiBackend> {
+    let resilient = |name: &str, backend: Arc<dyn AiBackend>| -> Arc<dyn AiBackend> {
+        Arc::new(resilience::ResilientBackend::new(name, backend, resilience))
+    };
+    match config {
+        AiBackendConfig::Agent(settings) => resilient(
+            "agent",
+            Arc::new(agent::AgentBackend::new(settings.clone(), http)),
+        ),
+        AiBackendConfig::Openai(settings) => resilient(
+            "openai",
+            Arc::new(openai::OpenAiBackend::new(settings.clone(), http)),
+        ),
+        AiBackendConfig::Dify(settings) => resilient(
+            "dify",
+            Arc::new(dify::DifyBackend::new(settings.clone(), http)),
+        ),
+        AiBackendConfig::Flowise(settings) => resilient(
+            "flowise",
+            Arc::new(flowise::FlowiseBackend::new(settings.clone(), http)),
+        ),
+        AiBackendConfig::Rasa(settings) => resilient(
+            "rasa",
+            Arc::new(rasa::RasaBackend::new(settings.clone(), http)),
+        ),
+        AiBackendConfig::Split { variants } => Arc::new(split::SplitBackend::new(
+            variants
+                .iter()
+                .map(|variant| split::SplitVariant {
+                    name: variant.name.clone(),
+                    weight: variant.weight,
+                    backend: build_backend(&variant.backend, resilience, http.clone()),
+                })
+                .collect(),
+        )),
+        AiBackendConfig::Shadow { primary, candidate } => Arc::new(shadow::ShadowBackend::new(
+            build_backend(primary, resilience, http.clone()),
+            build_backend(candidate, resilience, http),
+        )),
+    }
+}
+
+/// Plain text goes to the model as-is; anything else (unsupported types, attachments)
+/// is passed as the JSON `data` so the model can still say something sensible.
+pub fn user_text(data: &Value) -> String {
+    let text = data.get("text").and_then(Value::as_str).unwrap_or_default();
+    let only_text = data.get("attachments").is_none();
+    if !text.trim().is_empty() && only_text {
+        return text.to_string();
+    }
+    data.to_string()
+}
@@ -0,0 +1,83 @@
+use super::{AiBackend, AiError};
+use crate::{
+    models::ai::{InputRequest, LlmApiResponse},
+    services::moderation::Moderator,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Which side of the call a `ModeratingBackend` checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// User messages, before history: blocked ones are answered with the canned reply
+    /// without calling the backend, and never stored.
+    Input,
+    /// Replies, inside history: what gets stored is what the user sees.
+    Reply,
+}
+
+/// Wraps a backend so user messages or its replies go through `Moderator`.
+pub struct ModeratingBackend {
+    inner: Arc<dyn AiBackend>,
+    moderator: Arc<Moderator>,
+    stage: Stage,
+}
+
+impl ModeratingBackend {
+    pub fn new(inner: Arc<dyn AiBackend>, moderator: Arc<Moderator>, stage: Stage) -> Self {
+        Self {
+            inner,
+            moderator,
+            stage,
+        }
+    }
+}
+
+#[async_trait]
+impl AiBackend for ModeratingBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        if self.stage == Stage::Input {
+            if let Some(blocked) = self.moderator.screen_input(body).await {
+                return Ok(blocked);
+            }
+            return self.inner.send_user_message(body).await;
+        }
+        let mut res = self.inner.send_user_message(body).await?;
+        self.moderator.screen_reply(&body.thread_id, &mut res).await;
+        Ok(res)
+    }
+
+    /// A reply can't be checked before it is complete, so while replies are moderated the
+    /// deltas are held back and the caller gets the checked reply in one piece.
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        if self.stage == Stage::Input {
+            if let Some(blocked) = self.moderator.screen_input(body).await {
+                return Ok(blocked);
+            }
+            return self.inner.stream_user_message(body, deltas).await;
+        }
+        drop(deltas);
+        let (tx, mut rx) = mpsc::channel::<String>(32);
+        let discard = async { while rx.recv().await.is_some() {} };
+        let (result, ()) = tokio::join!(self.inner.stream_user_message(body, tx), discard);
+        let mut res = result?;
+        self.moderator.screen_reply(&body.thread_id, &mut res).await;
+        Ok(res)
+    }
+
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        self.inner.add_context(body).await
+    }
+
+    async fn finish_deferred(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        self.inner.finish_deferred(thread_id, res).await;
+        if self.stage == Stage::Reply {
+            self.moderator.screen_reply(thread_id, res).await;
+        }
+    }
+}
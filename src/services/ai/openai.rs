@@ -0,0 +1,84 @@
+use super::{AiBackend, AiError, user_text};
+use crate::{
+    config::OpenAiConfig,
+    models::{
+        ai::{InputRequest, LlmApiResponse},
+        openai::{ChatCompletionRequest, ChatCompletionResponse, ChatMessage},
+    },
+};
+use async_trait::async_trait;
+use reqwest::Url;
+
+/// OpenAI-compatible `/chat/completions` backend (OpenAI, vLLM, Ollama, llama.cpp, ...).
+/// These servers are stateless: earlier turns come from `InputRequest.history`, which the
+/// history store fills in (see `services::ai::history`).
+pub struct OpenAiBackend {
+    http: reqwest::Client,
+    config: OpenAiConfig,
+}
+
+impl OpenAiBackend {
+    pub fn new(config: OpenAiConfig, http: reqwest::Client) -> Self {
+        Self { http, config }
+    }
+
+    fn endpoint(&self) -> Result<Url, String> {
+        let mut url = self.config.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "OpenAI base URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .extend(["chat", "completions"]);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl AiBackend for OpenAiBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let mut messages = Vec::new();
+        if let Some(prompt) = &self.config.system_prompt {
+            messages.push(ChatMessage::new("system", prompt.clone()));
+        }
+        for turn in body.history.iter().flatten() {
+            messages.push(ChatMessage::new(&turn.role, turn.content.clone()));
+        }
+        messages.push(ChatMessage::new("user", user_text(&body.data)));
+
+        let request = ChatCompletionRequest {
+            model: &self.config.model,
+            messages: &messages,
+            temperature: self.config.temperature,
+        };
+
+        let mut req = self.http.post(self.endpoint()?).json(&request);
+        if let Some(key) = &self.config.api_key {
+            req = req.bearer_auth(key);
+        }
+        let res = req.send().await.map_err(AiError::Request)?;
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        let completion = res
+            .json::<ChatCompletionResponse>()
+            .await
+            .map_err(|e| AiError::Decode(e.to_string()))?;
+
+        let choice = completion
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| "completion has no choices".to_string())?;
+
+        Ok(LlmApiResponse {
+            next_step: "end".to_string(),
+            next_step_reason: choice
+                .finish_reason
+                .unwrap_or_else(|| "completed".to_string()),
+            response: choice.message.content.filter(|c| !c.trim().is_empty()),
+            parts: Vec::new(),
+            job_id: None,
+            schedule: Vec::new(),
+            handoff: false,
+        })
+    }
+}
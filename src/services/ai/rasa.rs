@@ -0,0 +1,74 @@
+use super::{AiBackend, AiError, user_text};
+use crate::{
+    config::RasaConfig,
+    models::{
+        ai::{InputRequest, LlmApiResponse},
+        rasa::{RasaBotMessage, RasaMessage},
+    },
+};
+use async_trait::async_trait;
+use reqwest::Url;
+
+/// Rasa REST channel. The `thread_id` is the Rasa `sender`, so each chat has its own tracker.
+/// Every bot utterance becomes its own outbound message (`LlmApiResponse.parts`).
+pub struct RasaBackend {
+    http: reqwest::Client,
+    config: RasaConfig,
+}
+
+impl RasaBackend {
+    pub fn new(config: RasaConfig, http: reqwest::Client) -> Self {
+        Self { http, config }
+    }
+
+    fn endpoint(&self) -> Result<Url, String> {
+        let mut url = self.config.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "Rasa base URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .extend(["webhooks", "rest", "webhook"]);
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl AiBackend for RasaBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let message = user_text(&body.data);
+        let res = self
+            .http
+            .post(self.endpoint()?)
+            .json(&RasaMessage {
+                sender: &body.thread_id,
+                message: &message,
+            })
+            .send()
+            .await
+            .map_err(AiError::Request)?;
+        if !res.status().is_success() {
+            return Err(AiError::Status(res.status()));
+        }
+        let messages = res
+            .json::<Vec<RasaBotMessage>>()
+            .await
+            .map_err(|e| AiError::Decode(e.to_string()))?;
+
+        // Images are sent as their URL; buttons and custom payloads are not supported.
+        let parts: Vec<String> = messages
+            .into_iter()
+            .flat_map(|m| [m.text, m.image])
+            .flatten()
+            .filter(|part| !part.trim().is_empty())
+            .collect();
+
+        Ok(LlmApiResponse {
+            next_step: "end".to_string(),
+            next_step_reason: "rasa".to_string(),
+            response: (!parts.is_empty()).then(|| parts.join("\n\n")),
+            parts,
+            job_id: None,
+            schedule: Vec::new(),
+            handoff: false,
+        })
+    }
+}
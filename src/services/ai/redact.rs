@@ -0,0 +1,80 @@
+use super::{AiBackend, AiError};
+use crate::{
+    models::ai::{InputRequest, LlmApiResponse},
+    services::redaction::Redactor,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Wraps a backend (history included) so personal data in user content is replaced by
+/// tokens before the call, and the tokens in the reply are turned back into the values.
+pub struct RedactingBackend {
+    inner: Arc<dyn AiBackend>,
+    redactor: Arc<Redactor>,
+}
+
+impl RedactingBackend {
+    pub fn new(inner: Arc<dyn AiBackend>, redactor: Arc<Redactor>) -> Self {
+        Self { inner, redactor }
+    }
+
+    fn redacted(&self, body: &InputRequest) -> InputRequest {
+        let mut req = body.clone();
+        self.redactor.redact_data(&req.thread_id, &mut req.data);
+        req
+    }
+}
+
+#[async_trait]
+impl AiBackend for RedactingBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let mut res = self.inner.send_user_message(&self.redacted(body)).await?;
+        self.redactor.rehydrate_response(&body.thread_id, &mut res);
+        Ok(res)
+    }
+
+    /// Deltas are re-hydrated on the way through; a token cut in two is held back until
+    /// its other half arrives.
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let (tx, mut rx) = mpsc::channel::<String>(32);
+        let thread_id = &body.thread_id;
+        let forward = async {
+            let mut pending = String::new();
+            while let Some(delta) = rx.recv().await {
+                pending.push_str(&delta);
+                let ready: String = pending
+                    .drain(..Redactor::complete_prefix(&pending))
+                    .collect();
+                if !ready.is_empty() {
+                    let _ = deltas
+                        .send(self.redactor.rehydrate(thread_id, &ready))
+                        .await;
+                }
+            }
+            if !pending.is_empty() {
+                let _ = deltas
+                    .send(self.redactor.rehydrate(thread_id, &pending))
+                    .await;
+            }
+        };
+        let req = self.redacted(body);
+        let (result, ()) = tokio::join!(self.inner.stream_user_message(&req, tx), forward);
+        let mut res = result?;
+        self.redactor.rehydrate_response(thread_id, &mut res);
+        Ok(res)
+    }
+
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        self.inner.add_context(&self.redacted(body)).await
+    }
+
+    async fn finish_deferred(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        self.inner.finish_deferred(thread_id, res).await;
+        self.redactor.rehydrate_response(thread_id, res);
+    }
+}
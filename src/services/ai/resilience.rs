@@ -0,0 +1,183 @@
+use super::{AiBackend, AiError};
+use crate::{
+    config::AiResilienceConfig,
+    models::ai::{InputRequest, LlmApiResponse},
+};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Wraps a backend that makes HTTP calls with bounded retries (exponential backoff with full
+/// jitter, only for connect errors and 5xx) and a circuit breaker: after
+/// `AI_BREAKER_THRESHOLD` failed turns in a row (timeouts included), calls fail fast for
+/// `AI_BREAKER_COOLDOWN_SECS`, then a single trial call decides whether the circuit closes
+/// again.
+pub struct ResilientBackend {
+    name: String,
+    inner: Arc<dyn AiBackend>,
+    retries: u32,
+    retry_base: Duration,
+    threshold: u32,
+    cooldown: Duration,
+    breaker: Mutex<Breaker>,
+}
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+impl ResilientBackend {
+    pub fn new(name: &str, inner: Arc<dyn AiBackend>, cfg: &AiResilienceConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            inner,
+            retries: cfg.retries,
+            retry_base: Duration::from_millis(cfg.retry_base_ms),
+            threshold: cfg.breaker_threshold,
+            cooldown: Duration::from_secs(cfg.breaker_cooldown_secs),
+            breaker: Mutex::new(Breaker::default()),
+        }
+    }
+
+    /// `Err` when the circuit is open. Once the cooldown is over one caller gets through as
+    /// the trial (`Ok(true)`); everyone else keeps failing fast until it comes back.
+    fn admit(&self) -> Result<bool, AiError> {
+        let mut breaker = self.breaker.lock().expect("breaker poisoned");
+        let Some(open_until) = breaker.open_until else {
+            return Ok(false);
+        };
+        if Instant::now() < open_until || breaker.trial_in_flight {
+            return Err(AiError::CircuitOpen(self.name.clone()));
+        }
+        breaker.trial_in_flight = true;
+        Ok(true)
+    }
+
+    fn record(&self, ok: bool) {
+        let mut breaker = self.breaker.lock().expect("breaker poisoned");
+        breaker.trial_in_flight = false;
+        if ok {
+            if breaker.open_until.take().is_some() {
+                info!("AI circuit for '{}' closed again", self.name);
+            }
+            breaker.failures = 0;
+            return;
+        }
+
+        breaker.failures += 1;
+        if self.threshold > 0 && breaker.failures >= self.threshold {
+            if breaker.open_until.is_none() {
+                warn!(
+                    "AI circuit for '{}' opened after {} failures, cooling down for {:?}",
+                    self.name, breaker.failures, self.cooldown
+                );
+            }
+            breaker.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+
+    /// Full jitter: a random delay between zero and `base * 2^attempt`.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap = (self.retry_base.as_millis() as u64).saturating_mul(2u64.saturating_pow(attempt));
+        Duration::from_millis(rand::random_range(0..=cap))
+    }
+
+    /// One attempt. While streaming, the deltas go through a channel of their own so we know
+    /// whether the user has already seen part of the reply; such attempts are never retried.
+    async fn attempt(
+        &self,
+        body: &InputRequest,
+        deltas: Option<&mpsc::Sender<String>>,
+    ) -> (Result<LlmApiResponse, AiError>, bool) {
+        let Some(deltas) = deltas else {
+            return (self.inner.send_user_message(body).await, false);
+        };
+
+        let (tx, mut rx) = mpsc::channel(32);
+        let forward = async {
+            let mut streamed = false;
+            while let Some(delta) = rx.recv().await {
+                streamed = true;
+                // The caller may have stopped listening; the reply is still finished.
+                let _ = deltas.send(delta).await;
+            }
+            streamed
+        };
+        tokio::join!(self.inner.stream_user_message(body, tx), forward)
+    }
+
+    async fn call(
+        &self,
+        body: &InputRequest,
+        deltas: Option<mpsc::Sender<String>>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let mut trial = TrialGuard {
+            breaker: &self.breaker,
+            armed: self.admit()?,
+        };
+
+        let mut attempt = 0;
+        let result = loop {
+            match self.attempt(body, deltas.as_ref()).await {
+                (Err(err), false) if err.is_retryable() && attempt < self.retries => {
+                    let delay = self.backoff(attempt);
+                    warn!(
+                        "AI call to '{}' failed ({}), retrying in {:?}",
+                        self.name, err, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                (result, _) => break result,
+            }
+        };
+
+        // Only failures that say the backend is unhealthy count towards the breaker.
+        self.record(!matches!(&result, Err(err) if err.is_unhealthy()));
+        trial.armed = false;
+        result
+    }
+}
+
+/// Frees the trial slot when the trial call is dropped before it is recorded (e.g. the
+/// `/v1/chat` client went away), so the circuit doesn't stay open for good.
+struct TrialGuard<'a> {
+    breaker: &'a Mutex<Breaker>,
+    armed: bool,
+}
+
+impl Drop for TrialGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.breaker
+                .lock()
+                .expect("breaker poisoned")
+                .trial_in_flight = false;
+        }
+    }
+}
+
+#[async_trait]
+impl AiBackend for ResilientBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        self.call(body, None).await
+    }
+
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        self.call(body, Some(deltas)).await
+    }
+
+    /// Not retried: a lost bit of context isn't worth holding the chat for.
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        self.inner.add_context(body).await
+    }
+}
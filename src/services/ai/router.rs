@@ -0,0 +1,101 @@
+use super::AiBackend;
+use crate::config::{AgentKnobs, AiRouteConfig};
+use std::sync::Arc;
+
+/// What an inbound message is matched on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteKey<'a> {
+    /// `waha`, `wacraft`, `matrix`, `signal`, `chat`, `webchat` or a generic provider name
+    pub provider: &'a str,
+    /// WAHA session
+    pub session: Option<&'a str>,
+    /// Wacraft messaging product (the receiving account)
+    pub messaging_product_id: Option<&'a str>,
+    /// Receiving number / account
+    pub to: Option<&'a str>,
+}
+
+/// The backend, knobs and thread prefix picked for a conversation.
+#[derive(Clone)]
+pub struct ResolvedRoute {
+    pub name: Option<String>,
+    pub backend: Arc<dyn AiBackend>,
+    pub knobs: AgentKnobs,
+    pub thread_prefix: Option<String>,
+}
+
+struct Route {
+    config: AiRouteConfig,
+    backend: Option<Arc<dyn AiBackend>>,
+}
+
+/// Picks the AI backend per conversation from the routing table (`AI_ROUTES_PATH`).
+/// Routes are checked in file order; the first match wins, otherwise `AI_BACKEND` is used.
+#[derive(Clone)]
+pub struct AiRouter {
+    default: Arc<dyn AiBackend>,
+    routes: Arc<Vec<Route>>,
+}
+
+impl AiRouter {
+    pub fn new(
+        default: Arc<dyn AiBackend>,
+        routes: Vec<(AiRouteConfig, Option<Arc<dyn AiBackend>>)>,
+    ) -> Self {
+        let routes = routes
+            .into_iter()
+            .map(|(config, backend)| Route { config, backend })
+            .collect();
+        Self {
+            default,
+            routes: Arc::new(routes),
+        }
+    }
+
+    pub fn resolve(&self, key: &RouteKey) -> ResolvedRoute {
+        let Some(route) = self.routes.iter().find(|r| matches(&r.config, key)) else {
+            return ResolvedRoute {
+                name: None,
+                backend: self.default.clone(),
+                knobs: AgentKnobs::default(),
+                thread_prefix: None,
+            };
+        };
+
+        ResolvedRoute {
+            name: Some(route.config.name.clone()),
+            backend: route
+                .backend
+                .clone()
+                .unwrap_or_else(|| self.default.clone()),
+            knobs: route.config.knobs.clone(),
+            thread_prefix: route.config.thread_prefix.clone(),
+        }
+    }
+}
+
+fn matches(route: &AiRouteConfig, key: &RouteKey) -> bool {
+    let m = &route.matcher;
+    let eq = |want: &Option<String>, got: Option<&str>| match want {
+        None => true,
+        Some(want) => got == Some(want.as_str()),
+    };
+    let eq_number = |want: &Option<String>, got: Option<&str>| match want {
+        None => true,
+        Some(want) => got.is_some_and(|got| normalize_number(got) == normalize_number(want)),
+    };
+
+    eq(&m.provider, Some(key.provider))
+        && eq(&m.session, key.session)
+        && eq(&m.messaging_product_id, key.messaging_product_id)
+        && eq_number(&m.to, key.to)
+}
+
+/// `+55 11 98888-8888`, `5511988888888` and `5511988888888@c.us` all compare equal.
+fn normalize_number(raw: &str) -> String {
+    let number = raw.split('@').next().unwrap_or(raw);
+    number
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
@@ -0,0 +1,99 @@
+use super::{AiBackend, AiError};
+use crate::models::ai::{InputRequest, LlmApiResponse};
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// Answers with `primary` and mirrors every request to `candidate` in the background.
+/// The candidate's reply is never sent; both replies and latencies are logged (target
+/// `shadow`) for offline comparison.
+pub struct ShadowBackend {
+    primary: Arc<dyn AiBackend>,
+    candidate: Arc<dyn AiBackend>,
+}
+
+impl ShadowBackend {
+    pub fn new(primary: Arc<dyn AiBackend>, candidate: Arc<dyn AiBackend>) -> Self {
+        Self { primary, candidate }
+    }
+
+    /// The primary streams when the caller asked for it; the candidate always answers in one
+    /// piece, since only its full reply is logged.
+    async fn answer(
+        &self,
+        body: &InputRequest,
+        deltas: Option<mpsc::Sender<String>>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let candidate = self.candidate.clone();
+        let shadow_req = body.clone();
+        let shadow = tokio::spawn(async move {
+            let started = Instant::now();
+            let result = candidate.send_user_message(&shadow_req).await;
+            (result, started.elapsed())
+        });
+
+        let started = Instant::now();
+        let result = match deltas {
+            Some(deltas) => self.primary.stream_user_message(body, deltas).await,
+            None => self.primary.send_user_message(body).await,
+        };
+        let primary_latency = started.elapsed();
+
+        let thread_id = body.thread_id.clone();
+        let primary_reply = summarize(&result);
+        // Don't hold the user's reply back while the candidate is still thinking.
+        tokio::spawn(async move {
+            let (candidate_reply, candidate_latency) = match shadow.await {
+                Ok((result, latency)) => (summarize(&result), Some(latency)),
+                Err(err) => (format!("task failed: {err}"), None),
+            };
+            info!(
+                target: "shadow",
+                thread_id = %thread_id,
+                primary_ms = primary_latency.as_millis() as u64,
+                candidate_ms = candidate_latency.map(|l| l.as_millis() as u64),
+                primary = %primary_reply,
+                candidate = %candidate_reply,
+                "shadow comparison"
+            );
+        });
+
+        result
+    }
+}
+
+#[async_trait]
+impl AiBackend for ShadowBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        self.answer(body, None).await
+    }
+
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        self.answer(body, Some(deltas)).await
+    }
+
+    /// Mirrored like turns, so the candidate's thread stays comparable.
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        let candidate = self.candidate.clone();
+        let shadow_req = body.clone();
+        tokio::spawn(async move {
+            if let Err(err) = candidate.add_context(&shadow_req).await {
+                info!(target: "shadow", thread_id = %shadow_req.thread_id, "candidate context failed: {err}");
+            }
+        });
+        self.primary.add_context(body).await
+    }
+}
+
+fn summarize(result: &Result<LlmApiResponse, AiError>) -> String {
+    match result {
+        Ok(res) => res.replies().join(" | "),
+        Err(err) => format!("error: {err}"),
+    }
+}
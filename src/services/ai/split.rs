@@ -0,0 +1,83 @@
+use super::{AiBackend, AiError};
+use crate::models::ai::{InputRequest, LlmApiResponse};
+use crate::store::threads::base_thread_id;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+pub struct SplitVariant {
+    pub name: String,
+    pub weight: u32,
+    pub backend: Arc<dyn AiBackend>,
+}
+
+/// Weighted A/B split. The variant is picked from a hash of the `thread_id` without its
+/// `#<generation>` suffix, so a chat stays on the same backend across resets (as long as the
+/// weights don't change).
+pub struct SplitBackend {
+    variants: Vec<SplitVariant>,
+    total_weight: u64,
+}
+
+impl SplitBackend {
+    pub fn new(variants: Vec<SplitVariant>) -> Self {
+        let total_weight = variants.iter().map(|v| u64::from(v.weight)).sum();
+        Self {
+            variants,
+            total_weight,
+        }
+    }
+
+    fn pick(&self, thread_id: &str) -> &SplitVariant {
+        let mut slot = fnv1a(base_thread_id(thread_id).as_bytes()) % self.total_weight.max(1);
+        for variant in &self.variants {
+            let weight = u64::from(variant.weight);
+            if slot < weight {
+                return variant;
+            }
+            slot -= weight;
+        }
+        // Only reachable if every weight is 0 (rejected when the config is loaded).
+        &self.variants[0]
+    }
+}
+
+#[async_trait]
+impl AiBackend for SplitBackend {
+    async fn send_user_message(&self, body: &InputRequest) -> Result<LlmApiResponse, AiError> {
+        let variant = self.pick(&body.thread_id);
+        debug!(
+            "Thread {} assigned to variant '{}'",
+            body.thread_id, variant.name
+        );
+        variant.backend.send_user_message(body).await
+    }
+
+    async fn stream_user_message(
+        &self,
+        body: &InputRequest,
+        deltas: mpsc::Sender<String>,
+    ) -> Result<LlmApiResponse, AiError> {
+        let variant = self.pick(&body.thread_id);
+        debug!(
+            "Thread {} assigned to variant '{}'",
+            body.thread_id, variant.name
+        );
+        variant.backend.stream_user_message(body, deltas).await
+    }
+
+    async fn add_context(&self, body: &InputRequest) -> Result<(), AiError> {
+        self.pick(&body.thread_id).backend.add_context(body).await
+    }
+}
+
+/// FNV-1a: stable across restarts and Rust versions, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
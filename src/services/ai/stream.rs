@@ -0,0 +1,67 @@
+/// Cuts a streamed reply into messages. A blank line always ends a message; once at least
+/// `min_chars` are buffered, the text is also cut after the last finished sentence, so long
+/// paragraphs don't hold everything back.
+pub struct ChunkSplitter {
+    buf: String,
+    min_chars: usize,
+}
+
+impl ChunkSplitter {
+    pub fn new(min_chars: usize) -> Self {
+        Self {
+            buf: String::new(),
+            min_chars,
+        }
+    }
+
+    /// Adds a delta and returns the messages it completed, if any.
+    pub fn push(&mut self, delta: &str) -> Vec<String> {
+        self.buf.push_str(delta);
+        let mut chunks = Vec::new();
+
+        while let Some(idx) = self.buf.find("\n\n") {
+            let rest = self.buf.split_off(idx);
+            let chunk = std::mem::replace(&mut self.buf, rest.trim_start().to_string());
+            push_trimmed(&mut chunks, &chunk);
+        }
+
+        if self.buf.chars().count() >= self.min_chars {
+            if let Some(end) = last_sentence_end(&self.buf) {
+                let rest = self.buf.split_off(end);
+                let chunk = std::mem::replace(&mut self.buf, rest.trim_start().to_string());
+                push_trimmed(&mut chunks, &chunk);
+            }
+        }
+
+        chunks
+    }
+
+    /// Whatever is left once the stream is over.
+    pub fn finish(self) -> Option<String> {
+        let rest = self.buf.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+fn push_trimmed(chunks: &mut Vec<String>, chunk: &str) {
+    let chunk = chunk.trim();
+    if !chunk.is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+/// Byte offset right after the last `.`, `!`, `?`, `…` or line break that is followed by
+/// whitespace. The text after it may still be growing, so a trailing `.` doesn't count.
+fn last_sentence_end(text: &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let Some(&(_, next)) = chars.peek() else {
+            break;
+        };
+        if c == '\n' || (matches!(c, '.' | '!' | '?' | '…') && next.is_whitespace()) {
+            end = Some(idx + c.len_utf8());
+        }
+    }
+    end
+}
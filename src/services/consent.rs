@@ -0,0 +1,101 @@
+use crate::{
+    config::{ConsentConfig, ControlStoreConfig},
+    models::consent::{ConsentRecord, ConsentStatus},
+    store::{
+        blocking,
+        consent::{ConsentStore, build_consent_store},
+    },
+};
+use chrono::{SecondsFormat, Utc};
+use std::sync::Arc;
+use tracing::info;
+
+/// Opt-out and opt-in keywords, and the consent record each contact leaves behind.
+pub struct Consent {
+    config: ConsentConfig,
+    store: Arc<dyn ConsentStore>,
+}
+
+impl Consent {
+    pub fn new(config: &ConsentConfig, store: &ControlStoreConfig) -> Result<Self, String> {
+        Ok(Self {
+            config: config.clone(),
+            store: build_consent_store(store)?,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// What the message asks for when it is nothing but a keyword (`stop`, `SAIR!`).
+    pub fn keyword(&self, body: &str) -> Option<ConsentStatus> {
+        if !self.config.enabled {
+            return None;
+        }
+        let word = body
+            .trim()
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_uppercase();
+        if self.config.opt_out_keywords.contains(&word) {
+            Some(ConsentStatus::OptedOut)
+        } else if self.config.opt_in_keywords.contains(&word) {
+            Some(ConsentStatus::OptedIn)
+        } else {
+            None
+        }
+    }
+
+    pub async fn record(
+        &self,
+        provider: &str,
+        chat_id: &str,
+        status: ConsentStatus,
+        keyword: &str,
+    ) -> Result<ConsentRecord, String> {
+        let record = ConsentRecord {
+            provider: provider.to_string(),
+            chat_id: chat_id.to_string(),
+            status,
+            keyword: keyword.trim().to_string(),
+            updated_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        };
+        let store = Arc::clone(&self.store);
+        let stored = record.clone();
+        blocking(move || store.set(&stored)).await?;
+        info!(
+            "{} {} ({})",
+            chat_id,
+            match status {
+                ConsentStatus::OptedOut => "opted out",
+                ConsentStatus::OptedIn => "opted in",
+            },
+            record.keyword
+        );
+        Ok(record)
+    }
+
+    /// Contacts that never sent a keyword count as opted in.
+    pub async fn status(&self, provider: &str, chat_id: &str) -> Result<ConsentStatus, String> {
+        let store = Arc::clone(&self.store);
+        let (provider, chat_id) = (provider.to_string(), chat_id.to_string());
+        let record = blocking(move || store.get(&provider, &chat_id)).await?;
+        Ok(record.map_or(ConsentStatus::OptedIn, |record| record.status))
+    }
+
+    /// Whether proactive messages to the contact must be blocked.
+    pub async fn is_opted_out(&self, provider: &str, chat_id: &str) -> Result<bool, String> {
+        if !self.config.enabled {
+            return Ok(false);
+        }
+        Ok(self.status(provider, chat_id).await? == ConsentStatus::OptedOut)
+    }
+
+    /// The confirmation for a keyword, if any.
+    pub fn reply(&self, status: ConsentStatus) -> Option<&str> {
+        match status {
+            ConsentStatus::OptedOut => self.config.opt_out_reply.as_deref(),
+            ConsentStatus::OptedIn => self.config.opt_in_reply.as_deref(),
+        }
+    }
+}
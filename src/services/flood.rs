@@ -0,0 +1,146 @@
+use crate::{
+    config::InboundLimitConfig, services::handoff::ChatRef, synch::rate_limiter::RateLimiter,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+// Above this many tracked chats, the ones neither blocked nor recently over the limit are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// What to do with an inbound message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Accept,
+    Drop,
+    /// Drop it, but tell the user to slow down (once per run of over-limit messages)
+    SlowDown,
+}
+
+// A chat that went over its limit.
+struct Offender {
+    strikes: u32,
+    since: Instant,
+    warned: bool,
+    blocked_until: Option<Instant>,
+}
+
+/// Flood protection for inbound messages: token buckets per chat and per session, and a
+/// temporary block for chats that keep going over theirs.
+pub struct FloodGuard {
+    config: InboundLimitConfig,
+    chats: Option<RateLimiter<String>>,
+    sessions: Option<RateLimiter<String>>,
+    offenders: Mutex<HashMap<String, Offender>>,
+}
+
+impl FloodGuard {
+    pub fn new(config: &InboundLimitConfig) -> Self {
+        Self {
+            config: config.clone(),
+            chats: (config.chat_per_minute > 0)
+                .then(|| RateLimiter::new(config.chat_burst, config.chat_per_minute)),
+            sessions: (config.session_per_minute > 0)
+                .then(|| RateLimiter::new(config.session_burst, config.session_per_minute)),
+            offenders: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes one token from the chat's and the session's buckets.
+    pub fn check(&self, chat: &ChatRef) -> Admission {
+        if self.chats.is_none() && self.sessions.is_none() {
+            return Admission::Accept;
+        }
+        let session = format!(
+            "{}:{}",
+            chat.provider,
+            chat.session.as_deref().unwrap_or_default()
+        );
+        let key = format!("{session}:{}", chat.chat_id);
+        let now = Instant::now();
+        let mut offenders = self.offenders.lock().expect("flood guard poisoned");
+
+        if offenders.len() > PRUNE_THRESHOLD {
+            let window = Duration::from_secs(self.config.block_window_secs);
+            offenders.retain(|_, o| {
+                o.blocked_until.is_some_and(|until| until > now)
+                    || now.duration_since(o.since) < window
+            });
+        }
+
+        if offenders
+            .get(&key)
+            .and_then(|o| o.blocked_until)
+            .is_some_and(|until| until > now)
+        {
+            debug!(
+                "Dropping message from {}: temporarily blocked",
+                chat.chat_id
+            );
+            return Admission::Drop;
+        }
+
+        if let Some(limiter) = &self.chats {
+            if limiter.try_acquire(&key).is_err() {
+                return self.strike(&mut offenders, key, &chat.chat_id, now);
+            }
+        }
+        if let Some(limiter) = &self.sessions {
+            if limiter.try_acquire(&session).is_err() {
+                warn!(
+                    "Session {} is over its inbound limit, dropping message from {}",
+                    session, chat.chat_id
+                );
+                return Admission::Drop;
+            }
+        }
+
+        // The next run of over-limit messages gets its own slow-down reply.
+        if let Some(offender) = offenders.get_mut(&key) {
+            offender.warned = false;
+        }
+        Admission::Accept
+    }
+
+    fn strike(
+        &self,
+        offenders: &mut HashMap<String, Offender>,
+        key: String,
+        chat_id: &str,
+        now: Instant,
+    ) -> Admission {
+        let offender = offenders.entry(key).or_insert(Offender {
+            strikes: 0,
+            since: now,
+            warned: false,
+            blocked_until: None,
+        });
+        if now.duration_since(offender.since) > Duration::from_secs(self.config.block_window_secs) {
+            offender.strikes = 0;
+            offender.since = now;
+        }
+        offender.strikes += 1;
+
+        if self.config.block_after > 0 && offender.strikes >= self.config.block_after {
+            warn!(
+                "Blocking {} for {}s after {} messages over its limit",
+                chat_id, self.config.block_secs, offender.strikes
+            );
+            offender.blocked_until = Some(now + Duration::from_secs(self.config.block_secs));
+            offender.strikes = 0;
+            offender.warned = false;
+            return Admission::Drop;
+        }
+        if self.config.slow_down_message.is_some() && !offender.warned {
+            offender.warned = true;
+            return Admission::SlowDown;
+        }
+        debug!("Dropping message from {}: over its inbound limit", chat_id);
+        Admission::Drop
+    }
+
+    pub fn slow_down_message(&self) -> Option<&str> {
+        self.config.slow_down_message.as_deref()
+    }
+}
@@ -0,0 +1,159 @@
+use crate::config::GenericReplyTemplate;
+use serde_json::Value;
+use url::form_urlencoded::byte_serialize;
+
+/// Values substituted into a provider's reply template.
+pub struct ReplyVars<'a> {
+    pub chat_id: &'a str,
+    pub message_id: &'a str,
+    pub text: &'a str,
+}
+
+impl ReplyVars<'_> {
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{{chat_id}}", self.chat_id)
+            .replace("{{message_id}}", self.message_id)
+            .replace("{{text}}", self.text)
+    }
+
+    // Values end up inside the URL, so encode them as a single path/query component
+    // (`%20` rather than the form-style `+`, which is only valid in queries).
+    fn render_url(&self, template: &str) -> String {
+        let encode = |value: &str| {
+            byte_serialize(value.as_bytes())
+                .collect::<String>()
+                .replace('+', "%20")
+        };
+        template
+            .replace("{{chat_id}}", &encode(self.chat_id))
+            .replace("{{message_id}}", &encode(self.message_id))
+            .replace("{{text}}", &encode(self.text))
+    }
+
+    fn render_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(s) => Value::String(self.render(s)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.render_value(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.render_value(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+pub async fn send_text_message(
+    http: &reqwest::Client,
+    template: &GenericReplyTemplate,
+    vars: ReplyVars<'_>,
+) -> Result<(), String> {
+    let url = vars.render_url(&template.url);
+
+    let mut req = http.request(template.method.clone(), &url);
+    for (name, value) in &template.headers {
+        req = req.header(name.as_str(), vars.render(value));
+    }
+    if let Some(body) = &template.body {
+        req = req.json(&vars.render_value(body));
+    }
+
+    let res = req
+        .send()
+        .await
+        .map_err(|e| format!("request error: {e}"))?;
+    if !res.status().is_success() {
+        return Err(format!("reply status {}", res.status()));
+    }
+    Ok(())
+}
+
+/// Turns a field expression into a JSON pointer. Pointers (`/a/b/0`) are kept as they are and
+/// the JSONPath subset `$.a.b[0]` / `$['a']` is converted; anything else (`$..a`, wildcards,
+/// filters, slices) is `None`.
+pub fn field_pointer(expr: &str) -> Option<String> {
+    if expr.is_empty() || expr.starts_with('/') {
+        return Some(expr.to_string());
+    }
+    let mut pointer = String::new();
+    let mut rest = expr.strip_prefix('$')?;
+    let mut push = |segment: &str| {
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    };
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let segment = &after_dot[..end];
+            if segment.is_empty() || segment == "*" {
+                return None;
+            }
+            push(segment);
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let quote = after_bracket
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '\'' | '"'));
+            let (segment, after) = match quote {
+                Some(quote) => {
+                    let inner = &after_bracket[1..];
+                    let end = inner.find(&format!("{quote}]"))?;
+                    (&inner[..end], &inner[end + 2..])
+                }
+                None => {
+                    let end = after_bracket.find(']')?;
+                    let index = &after_bracket[..end];
+                    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    (index, &after_bracket[end + 1..])
+                }
+            };
+            push(segment);
+            rest = after;
+        } else {
+            return None;
+        }
+    }
+
+    Some(pointer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointers_and_simple_paths_are_accepted() {
+        assert_eq!(field_pointer("/a/b/0").as_deref(), Some("/a/b/0"));
+        assert_eq!(field_pointer("$.a.b[0]").as_deref(), Some("/a/b/0"));
+        assert_eq!(field_pointer("$['a'][\"b\"]").as_deref(), Some("/a/b"));
+        assert_eq!(field_pointer("$['a.b]/c']").as_deref(), Some("/a.b]~1c"));
+        assert_eq!(field_pointer("$").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn unsupported_paths_are_rejected() {
+        for expr in [
+            "a.b",
+            "$..a",
+            "$.a.",
+            "$.*",
+            "$.a[*]",
+            "$.a[-1]",
+            "$.a[0:2]",
+            "$.a[?(@.b)]",
+            "$[a]",
+            "$['a]",
+            "$a",
+        ] {
+            assert_eq!(field_pointer(expr), None, "{expr}");
+        }
+    }
+}
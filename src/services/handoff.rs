@@ -0,0 +1,157 @@
+use crate::{
+    config::{ControlStoreConfig, HandoffConfig},
+    models::handoff::ChatPause,
+    store::{
+        blocking,
+        pause::{PauseStore, build_pause_store},
+    },
+    synch::sent_messages::SentMessages,
+};
+use chrono::{Duration as ChronoDuration, SecondsFormat, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// How long our own sends are remembered while waiting for their `fromMe` echo
+const SENT_TTL: Duration = Duration::from_secs(600);
+
+/// `chat_id` of a pause that covers every chat of its provider and session.
+pub const WHOLE_SESSION: &str = "*";
+
+/// A conversation as the pause store sees it.
+#[derive(Debug, Clone)]
+pub struct ChatRef {
+    pub provider: String,
+    /// WAHA session or Wacraft messaging product
+    pub session: Option<String>,
+    pub chat_id: String,
+}
+
+/// Human handoff: chats where AI replies are paused while an operator talks to the user.
+pub struct Handoff {
+    config: HandoffConfig,
+    store: Arc<dyn PauseStore>,
+    sent: SentMessages,
+}
+
+impl Handoff {
+    pub fn new(config: &HandoffConfig, store: &ControlStoreConfig) -> Result<Self, String> {
+        Ok(Self {
+            config: config.clone(),
+            store: build_pause_store(store)?,
+            sent: SentMessages::new(SENT_TTL),
+        })
+    }
+
+    /// Whether AI replies are paused for the chat, or for its whole session. Store errors
+    /// don't block the bot.
+    pub async fn is_paused(&self, chat: &ChatRef) -> bool {
+        let store = Arc::clone(&self.store);
+        let target = chat.clone();
+        let paused = blocking(move || {
+            for chat_id in [target.chat_id.as_str(), WHOLE_SESSION] {
+                if store
+                    .get(&target.provider, target.session.as_deref(), chat_id)?
+                    .is_some()
+                {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+        .await;
+        paused.unwrap_or_else(|err| {
+            warn!("Failed to read pause of {}: {}", chat.chat_id, err);
+            false
+        })
+    }
+
+    /// Pauses the chat for `HANDOFF_PAUSE_SECS` (or until resumed when 0).
+    pub fn pause(&self, chat: &ChatRef, reason: &str) {
+        if let Err(err) = self.pause_for(chat, reason, self.config.pause_secs) {
+            warn!("Failed to pause {}: {}", chat.chat_id, err);
+        }
+    }
+
+    /// Pauses the chat (or the whole session, with `chat_id` `*`) for `secs`, or until
+    /// resumed when 0.
+    pub fn pause_for(&self, chat: &ChatRef, reason: &str, secs: u64) -> Result<ChatPause, String> {
+        let now = Utc::now();
+        let until = (secs > 0)
+            .then(|| {
+                i64::try_from(secs)
+                    .ok()
+                    .and_then(ChronoDuration::try_seconds)
+                    .and_then(|ttl| now.checked_add_signed(ttl))
+                    .map(|until| until.to_rfc3339_opts(SecondsFormat::Secs, false))
+                    .ok_or_else(|| format!("pause of {secs}s is too long"))
+            })
+            .transpose()?;
+        let pause = ChatPause {
+            provider: chat.provider.clone(),
+            session: chat.session.clone(),
+            chat_id: chat.chat_id.clone(),
+            reason: reason.to_string(),
+            paused_at: now.to_rfc3339_opts(SecondsFormat::Secs, false),
+            until,
+        };
+        self.store.pause(&pause)?;
+        info!(
+            "Paused AI replies for {} ({}, until {})",
+            chat.chat_id,
+            reason,
+            pause.until.as_deref().unwrap_or("resumed")
+        );
+        Ok(pause)
+    }
+
+    /// Returns `false` when the chat (or session, with `chat_id` `*`) wasn't paused.
+    pub fn resume(&self, chat: &ChatRef) -> Result<bool, String> {
+        let resumed = self
+            .store
+            .resume(&chat.provider, chat.session.as_deref(), &chat.chat_id)?;
+        if resumed {
+            info!("Resumed AI replies for {}", chat.chat_id);
+        }
+        Ok(resumed)
+    }
+
+    /// Every active pause, oldest first.
+    pub fn list(&self) -> Result<Vec<ChatPause>, String> {
+        self.store.list()
+    }
+
+    fn tracks_own_messages(&self) -> bool {
+        self.config.detect_operator || self.config.sync_operator
+    }
+
+    /// Remembers a message we are about to send, so its echo (WAHA `fromMe`, Wacraft
+    /// outbound event) is not mistaken for an operator.
+    pub fn record_sent(&self, chat_id: &str, body: &str) {
+        if self.tracks_own_messages() {
+            self.sent.record(chat_id, body);
+        }
+    }
+
+    /// Handles a message sent from the bot's own account. Our echoes are ignored; anything
+    /// else came from a human: with `HANDOFF_DETECT_OPERATOR` the resume command resumes
+    /// the chat and every other message pauses it. Returns `true` when the message should
+    /// be synced to the agent as operator context (`HANDOFF_SYNC_OPERATOR`).
+    pub fn on_own_message(&self, chat: &ChatRef, body: &str) -> bool {
+        if !self.tracks_own_messages() || self.sent.take(&chat.chat_id, body) {
+            return false;
+        }
+        if !self.config.resume_command.is_empty() && body.trim() == self.config.resume_command {
+            if self.config.detect_operator {
+                if let Err(err) = self.resume(chat) {
+                    warn!("Failed to resume {}: {}", chat.chat_id, err);
+                }
+            }
+            return false;
+        }
+        if self.config.detect_operator {
+            self.pause(chat, "operator");
+        }
+        self.config.sync_operator && !body.trim().is_empty()
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{config::BotLoopConfig, services::handoff::ChatRef};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Above this many tracked chats, the ones without messages in the window are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Default)]
+struct ChatActivity {
+    /// Arrival of the inbound messages within the window
+    inbound: VecDeque<Instant>,
+    /// Normalized text of the last inbound message and how often it came in a row
+    last_text: String,
+    repeats: u32,
+    /// When our last reply went out, until the next inbound message
+    replied_at: Option<Instant>,
+    fast_replies: u32,
+}
+
+/// Spots chats where the bot is talking to another auto-responder: too many messages in a
+/// short window, the same message over and over, or answers faster than anyone can type.
+pub struct LoopDetector {
+    config: BotLoopConfig,
+    chats: Mutex<HashMap<String, ChatActivity>>,
+}
+
+impl LoopDetector {
+    pub fn new(config: &BotLoopConfig) -> Self {
+        Self {
+            config: config.clone(),
+            chats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an inbound message. Returns why the chat looks like a loop, if it does; the
+    /// chat starts over clean then.
+    pub fn on_inbound(&self, chat: &ChatRef, body: &str) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let config = &self.config;
+        let window = Duration::from_secs(config.window_secs);
+        let now = Instant::now();
+        let key = chat_key(chat);
+        let mut chats = self.chats.lock().expect("loop detector poisoned");
+
+        if chats.len() > PRUNE_THRESHOLD {
+            chats.retain(|_, activity| {
+                activity
+                    .inbound
+                    .back()
+                    .is_some_and(|at| now.duration_since(*at) < window)
+            });
+        }
+
+        let activity = chats.entry(key.clone()).or_default();
+
+        activity.inbound.push_back(now);
+        while activity
+            .inbound
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > window)
+        {
+            activity.inbound.pop_front();
+        }
+
+        // Media and empty messages neither repeat nor break a run.
+        let text = normalize(body);
+        if !text.is_empty() {
+            if text == activity.last_text {
+                activity.repeats += 1;
+            } else {
+                activity.last_text = text;
+                activity.repeats = 1;
+            }
+        }
+
+        match activity.replied_at.take() {
+            Some(at) if now.duration_since(at) < Duration::from_millis(config.min_reply_ms) => {
+                activity.fast_replies += 1;
+            }
+            Some(_) => activity.fast_replies = 0,
+            None => {}
+        }
+
+        let reason = if config.max_messages > 0
+            && activity.inbound.len() > config.max_messages as usize
+        {
+            Some(format!(
+                "{} messages in {}s",
+                activity.inbound.len(),
+                config.window_secs
+            ))
+        } else if config.max_repeats > 0 && activity.repeats >= config.max_repeats {
+            Some(format!("same message {} times in a row", activity.repeats))
+        } else if config.max_fast_replies > 0 && activity.fast_replies >= config.max_fast_replies {
+            Some(format!(
+                "{} answers within {}ms of our reply",
+                activity.fast_replies, config.min_reply_ms
+            ))
+        } else {
+            None
+        };
+        if reason.is_some() {
+            chats.remove(&key);
+        }
+        reason
+    }
+
+    /// Records that the bot just answered the chat.
+    pub fn on_reply(&self, chat: &ChatRef) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut chats = self.chats.lock().expect("loop detector poisoned");
+        if let Some(activity) = chats.get_mut(&chat_key(chat)) {
+            activity.replied_at = Some(Instant::now());
+        }
+    }
+}
+
+fn chat_key(chat: &ChatRef) -> String {
+    format!(
+        "{}:{}:{}",
+        chat.provider,
+        chat.session.as_deref().unwrap_or_default(),
+        chat.chat_id
+    )
+}
+
+/// Letters only, lowercased, so messages differing in numbers, punctuation or spacing
+/// (order ids, timestamps, emoji) count as the same.
+fn normalize(body: &str) -> String {
+    body.chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
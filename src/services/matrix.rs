@@ -0,0 +1,184 @@
+use crate::{
+    config::MatrixConfig,
+    models::matrix::{MatrixSyncResponse, MatrixTextOut, MatrixTyping, MatrixWhoAmI},
+    utils::check_status,
+};
+use chrono::Utc;
+use reqwest::Url;
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::OnceCell;
+
+/// How long the homeserver keeps the typing notification alive if we never stop it.
+const TYPING_TIMEOUT_MS: u64 = 30_000;
+
+#[derive(Clone)]
+pub struct MatrixClient {
+    http: reqwest::Client,
+    config: Arc<MatrixConfig>,
+    user_id: Arc<OnceCell<String>>,
+    txn_counter: Arc<AtomicU64>,
+}
+
+impl MatrixClient {
+    pub fn new(config: MatrixConfig, http: reqwest::Client) -> Self {
+        let user_id = OnceCell::new_with(config.user_id.clone());
+        Self {
+            http,
+            config: Arc::new(config),
+            user_id: Arc::new(user_id),
+            txn_counter: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn config(&self) -> &MatrixConfig {
+        &self.config
+    }
+
+    /// The bot's own user id, resolved once through `/account/whoami` if not configured.
+    pub async fn user_id(&self) -> Result<String, String> {
+        self.user_id
+            .get_or_try_init(|| async {
+                let url = self.endpoint(&["account", "whoami"])?;
+                let res = self
+                    .http
+                    .get(url)
+                    .bearer_auth(&self.config.access_token)
+                    .send()
+                    .await
+                    .map_err(|err| format!("Failed to query Matrix whoami: {err}"))?;
+                let res = check_status(res, "Matrix whoami").await?;
+                res.json::<MatrixWhoAmI>()
+                    .await
+                    .map(|who| who.user_id)
+                    .map_err(|err| format!("Failed to parse Matrix whoami response: {err}"))
+            })
+            .await
+            .cloned()
+    }
+
+    /// Long-polls `/sync`. `timeout_ms = 0` returns immediately with the current state.
+    pub async fn sync(
+        &self,
+        since: Option<&str>,
+        timeout_ms: u64,
+    ) -> Result<MatrixSyncResponse, String> {
+        let mut url = self.endpoint(&["sync"])?;
+        {
+            let filter = json!({
+                "presence": { "types": [] },
+                "account_data": { "types": [] },
+                "room": {
+                    "timeline": { "types": ["m.room.message"] },
+                    "ephemeral": { "types": [] },
+                    "state": { "lazy_load_members": true },
+                },
+            });
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair("timeout", &timeout_ms.to_string());
+            pairs.append_pair("filter", &filter.to_string());
+            if let Some(since) = since {
+                pairs.append_pair("since", since);
+            }
+        }
+
+        let res = self
+            .http
+            .get(url)
+            .bearer_auth(&self.config.access_token)
+            // Leave room for the server-side long-poll before giving up.
+            .timeout(std::time::Duration::from_millis(timeout_ms + 30_000))
+            .send()
+            .await
+            .map_err(|err| format!("Failed to sync with Matrix: {err}"))?;
+        let res = check_status(res, "Matrix sync").await?;
+        res.json::<MatrixSyncResponse>()
+            .await
+            .map_err(|err| format!("Failed to parse Matrix sync response: {err}"))
+    }
+
+    pub async fn send_text_message(&self, room_id: &str, body: &str) -> Result<(), String> {
+        let txn_id = format!(
+            "ai-adapter-{}-{}",
+            Utc::now().timestamp_millis(),
+            self.txn_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        let url = self.endpoint(&["rooms", room_id, "send", "m.room.message", &txn_id])?;
+        let payload = MatrixTextOut {
+            msgtype: "m.text".to_string(),
+            body: body.to_string(),
+        };
+
+        let res = self
+            .http
+            .put(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send Matrix message: {err}"))?;
+        check_status(res, "Matrix send message").await?;
+        Ok(())
+    }
+
+    pub async fn set_typing(&self, room_id: &str, typing: bool) -> Result<(), String> {
+        let user_id = self.user_id().await?;
+        let url = self.endpoint(&["rooms", room_id, "typing", &user_id])?;
+        let payload = MatrixTyping {
+            typing,
+            timeout: typing.then_some(TYPING_TIMEOUT_MS),
+        };
+
+        let res = self
+            .http
+            .put(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| format!("Failed to update Matrix typing state: {err}"))?;
+        check_status(res, "Matrix typing").await?;
+        Ok(())
+    }
+
+    pub async fn send_read_receipt(&self, room_id: &str, event_id: &str) -> Result<(), String> {
+        let url = self.endpoint(&["rooms", room_id, "receipt", "m.read", event_id])?;
+        let res = self
+            .http
+            .post(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&json!({}))
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send Matrix read receipt: {err}"))?;
+        check_status(res, "Matrix read receipt").await?;
+        Ok(())
+    }
+
+    pub async fn join_room(&self, room_id: &str) -> Result<(), String> {
+        let url = self.endpoint(&["join", room_id])?;
+        let res = self
+            .http
+            .post(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&json!({}))
+            .send()
+            .await
+            .map_err(|err| format!("Failed to join Matrix room: {err}"))?;
+        check_status(res, "Matrix join").await?;
+        Ok(())
+    }
+
+    /// Builds `{homeserver}/_matrix/client/v3/<segments...>`, percent-encoding each segment
+    /// (room ids and user ids contain `!`, `@` and `:`).
+    fn endpoint(&self, segments: &[&str]) -> Result<Url, String> {
+        let mut url = self.config.homeserver_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "Matrix homeserver URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(segments);
+        Ok(url)
+    }
+}
@@ -1,3 +1,14 @@
+pub mod access;
 pub mod ai;
+pub mod consent;
+pub mod flood;
+pub mod generic;
+pub mod handoff;
+pub mod loops;
+pub mod matrix;
+pub mod moderation;
+pub mod redaction;
+pub mod signal;
 pub mod wacraft;
 pub mod waha;
+pub mod webchat;
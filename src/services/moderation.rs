@@ -0,0 +1,450 @@
+use crate::{
+    config::{Config, ModerationAction, ModerationConfig, ModerationHttpConfig, ModerationRules},
+    models::{
+        ai::{InputRequest, LlmApiResponse},
+        moderation::{ModerationDirection, ModerationRequest, ModerationResult},
+    },
+};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
+
+// A reply quoting this many words of a system prompt in a row leaks it. Shorter prompts
+// aren't checked.
+const LEAK_WINDOW_WORDS: usize = 8;
+
+/// What a check makes of a text.
+pub enum Verdict {
+    Pass,
+    Flag {
+        reason: String,
+        /// The text with the flagged parts masked, when the check can do that
+        rewrite: Option<String>,
+    },
+}
+
+/// One moderation check. Checks run in order; with `action: rewrite` each one sees the
+/// text as rewritten by the ones before.
+#[async_trait]
+pub trait ModerationCheck: Send + Sync {
+    async fn check(&self, direction: ModerationDirection, thread_id: &str, text: &str) -> Verdict;
+}
+
+/// Whole words or phrases, case-insensitive. Rewrites mask them with `*`.
+struct KeywordCheck {
+    regex: Regex,
+}
+
+#[async_trait]
+impl ModerationCheck for KeywordCheck {
+    async fn check(&self, _: ModerationDirection, _: &str, text: &str) -> Verdict {
+        let Some(found) = self.regex.find(text) else {
+            return Verdict::Pass;
+        };
+        Verdict::Flag {
+            reason: format!("banned term '{}'", found.as_str()),
+            rewrite: Some(
+                self.regex
+                    .replace_all(text, |caps: &regex::Captures| {
+                        "*".repeat(caps[0].chars().count())
+                    })
+                    .into_owned(),
+            ),
+        }
+    }
+}
+
+/// A regular expression. Rewrites replace its matches with `***`.
+struct PatternCheck {
+    regex: Regex,
+}
+
+#[async_trait]
+impl ModerationCheck for PatternCheck {
+    async fn check(&self, _: ModerationDirection, _: &str, text: &str) -> Verdict {
+        if !self.regex.is_match(text) {
+            return Verdict::Pass;
+        }
+        Verdict::Flag {
+            reason: format!("pattern '{}'", self.regex.as_str()),
+            rewrite: Some(self.regex.replace_all(text, "***").into_owned()),
+        }
+    }
+}
+
+/// Links (`http(s)://…` or `www.…`) must point to an allowed domain or one of its
+/// subdomains. Rewrites drop the other links.
+struct LinkCheck {
+    links: Regex,
+    allowed: Vec<String>,
+}
+
+impl LinkCheck {
+    fn allows(&self, link: &str) -> bool {
+        let link = if link.contains("://") {
+            link.to_string()
+        } else {
+            format!("http://{link}")
+        };
+        let Some(host) = Url::parse(&link)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+        else {
+            return false;
+        };
+        self.allowed
+            .iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+    }
+}
+
+#[async_trait]
+impl ModerationCheck for LinkCheck {
+    async fn check(&self, _: ModerationDirection, _: &str, text: &str) -> Verdict {
+        let Some(link) = self
+            .links
+            .find_iter(text)
+            .map(|found| found.as_str())
+            .find(|link| !self.allows(link))
+        else {
+            return Verdict::Pass;
+        };
+        Verdict::Flag {
+            reason: format!("link to {link}"),
+            rewrite: Some(
+                self.links
+                    .replace_all(text, |caps: &regex::Captures| {
+                        if self.allows(&caps[0]) {
+                            caps[0].to_string()
+                        } else {
+                            "[link removed]".to_string()
+                        }
+                    })
+                    .into_owned(),
+            ),
+        }
+    }
+}
+
+/// Replies containing a leak marker or quoting an OpenAI system prompt. Never rewritten.
+struct LeakCheck {
+    markers: Vec<String>,
+    prompt_windows: HashSet<String>,
+}
+
+#[async_trait]
+impl ModerationCheck for LeakCheck {
+    async fn check(&self, _: ModerationDirection, _: &str, text: &str) -> Verdict {
+        let lowered = text.to_lowercase();
+        let leaked = self.markers.iter().any(|marker| lowered.contains(marker))
+            || words(text)
+                .windows(LEAK_WINDOW_WORDS)
+                .any(|window| self.prompt_windows.contains(&window.join(" ")));
+        if !leaked {
+            return Verdict::Pass;
+        }
+        Verdict::Flag {
+            reason: "system prompt leaked".to_string(),
+            rewrite: None,
+        }
+    }
+}
+
+/// Asks `MODERATION_URL`. A failed call lets the text through, or blocks it with
+/// `MODERATION_FAIL_CLOSED=true`.
+struct HttpCheck {
+    http: reqwest::Client,
+    config: ModerationHttpConfig,
+    fail_closed: bool,
+}
+
+#[async_trait]
+impl ModerationCheck for HttpCheck {
+    async fn check(&self, direction: ModerationDirection, thread_id: &str, text: &str) -> Verdict {
+        let mut request = self
+            .http
+            .post(self.config.url.clone())
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .json(&ModerationRequest {
+                direction,
+                thread_id,
+                text,
+            });
+        if let Some(key) = &self.config.api_key {
+            request = request.bearer_auth(key);
+        }
+        let result = async {
+            request
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<ModerationResult>()
+                .await
+        }
+        .await;
+
+        match result {
+            Ok(res) if res.flagged => Verdict::Flag {
+                reason: res
+                    .reason
+                    .unwrap_or_else(|| "flagged by the moderation endpoint".to_string()),
+                rewrite: res.text,
+            },
+            Ok(_) => Verdict::Pass,
+            Err(err) if self.fail_closed => {
+                warn!(
+                    "Moderation call failed for {}, blocking: {}",
+                    thread_id, err
+                );
+                Verdict::Flag {
+                    reason: "moderation endpoint unavailable".to_string(),
+                    rewrite: None,
+                }
+            }
+            Err(err) => {
+                warn!(
+                    "Moderation call failed for {}, letting the text through: {}",
+                    thread_id, err
+                );
+                Verdict::Pass
+            }
+        }
+    }
+}
+
+// The checks of one direction.
+struct Stage {
+    direction: ModerationDirection,
+    checks: Vec<Box<dyn ModerationCheck>>,
+    action: ModerationAction,
+    reply: Option<String>,
+}
+
+impl Stage {
+    fn new(
+        direction: ModerationDirection,
+        rules: &ModerationRules,
+        config: &ModerationConfig,
+        system_prompts: &[&str],
+        http: &reqwest::Client,
+    ) -> Result<Self, String> {
+        let compile = |pattern: &str| {
+            Regex::new(pattern)
+                .map_err(|err| format!("Invalid moderation pattern {pattern}: {err}"))
+        };
+        let mut checks: Vec<Box<dyn ModerationCheck>> = Vec::new();
+
+        let keywords: Vec<String> = rules
+            .keywords
+            .iter()
+            .map(|k| k.trim())
+            .filter(|k| !k.is_empty())
+            .map(regex::escape)
+            .collect();
+        if !keywords.is_empty() {
+            checks.push(Box::new(KeywordCheck {
+                regex: compile(&format!(r"(?i)\b(?:{})\b", keywords.join("|")))?,
+            }));
+        }
+        for pattern in &rules.patterns {
+            checks.push(Box::new(PatternCheck {
+                regex: compile(pattern)?,
+            }));
+        }
+        if let Some(allowed) = &rules.allowed_domains {
+            checks.push(Box::new(LinkCheck {
+                links: compile(r#"(?i)\b(?:https?://|www\.)[^\s<>"'()]*[^\s<>"'().,;:!?]"#)?,
+                allowed: allowed
+                    .iter()
+                    .map(|d| d.trim().trim_start_matches("*.").to_lowercase())
+                    .collect(),
+            }));
+        }
+        if direction == ModerationDirection::Outbound {
+            let prompt_windows: HashSet<String> = system_prompts
+                .iter()
+                .flat_map(|prompt| {
+                    words(prompt)
+                        .windows(LEAK_WINDOW_WORDS)
+                        .map(|window| window.join(" "))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            if !rules.leak_markers.is_empty() || !prompt_windows.is_empty() {
+                checks.push(Box::new(LeakCheck {
+                    markers: rules
+                        .leak_markers
+                        .iter()
+                        .map(|m| m.trim().to_lowercase())
+                        .filter(|m| !m.is_empty())
+                        .collect(),
+                    prompt_windows,
+                }));
+            }
+        }
+        // Last, so the endpoint only sees what passed the local checks.
+        if rules.http {
+            let endpoint = config
+                .http
+                .clone()
+                .ok_or_else(|| "MODERATION_URL is required for `http: true`".to_string())?;
+            checks.push(Box::new(HttpCheck {
+                http: http.clone(),
+                config: endpoint,
+                fail_closed: config.fail_closed,
+            }));
+        }
+
+        Ok(Self {
+            direction,
+            checks,
+            action: rules.action,
+            reply: rules.reply.clone().filter(|r| !r.trim().is_empty()),
+        })
+    }
+
+    /// The text to send, rewritten where the action allows it, or why it is blocked.
+    async fn run(&self, thread_id: &str, text: &str) -> Result<String, String> {
+        let mut text = text.to_string();
+        for check in &self.checks {
+            match check.check(self.direction, thread_id, &text).await {
+                Verdict::Pass => {}
+                Verdict::Flag {
+                    reason,
+                    rewrite: Some(rewrite),
+                } if self.action == ModerationAction::Rewrite => {
+                    debug!("Rewrote reply for {}: {}", thread_id, reason);
+                    text = rewrite;
+                }
+                Verdict::Flag { reason, .. } => return Err(reason),
+            }
+        }
+        Ok(text)
+    }
+
+    fn blocked_reply(&self, reason: String) -> LlmApiResponse {
+        LlmApiResponse {
+            next_step: "moderated".to_string(),
+            next_step_reason: reason,
+            response: self.reply.clone(),
+            parts: Vec::new(),
+            job_id: None,
+            schedule: Vec::new(),
+            handoff: false,
+        }
+    }
+}
+
+/// Content moderation around the AI call (`MODERATION_PATH`): user messages failing the
+/// inbound checks never reach the AI and get the canned reply instead; replies failing the
+/// outbound checks are blocked or rewritten before they are sent.
+pub struct Moderator {
+    inbound: Option<Stage>,
+    outbound: Option<Stage>,
+}
+
+impl Moderator {
+    /// `None` while neither direction has rules. Replies are also checked against the
+    /// OpenAI system prompts of the default backend and every route.
+    pub fn new(cfg: &Config, http: reqwest::Client) -> Result<Option<Self>, String> {
+        let config = &cfg.moderation;
+        if config.inbound.is_none() && config.outbound.is_none() {
+            return Ok(None);
+        }
+        let system_prompts: Vec<&str> = cfg
+            .ai_routes
+            .iter()
+            .filter_map(|route| route.backend.as_ref())
+            .chain([&cfg.ai_backend])
+            .flat_map(|backend| backend.system_prompts())
+            .collect();
+        let stage = |direction, rules: &Option<ModerationRules>| {
+            rules
+                .as_ref()
+                .map(|rules| Stage::new(direction, rules, config, &system_prompts, &http))
+                .transpose()
+        };
+
+        Ok(Some(Self {
+            inbound: stage(ModerationDirection::Inbound, &config.inbound)?,
+            outbound: stage(ModerationDirection::Outbound, &config.outbound)?,
+        }))
+    }
+
+    /// Checks the user content of a request: `data.text` and attachment captions. Returns
+    /// the reply to answer instead of calling the AI when it is blocked.
+    pub async fn screen_input(&self, req: &InputRequest) -> Option<LlmApiResponse> {
+        let stage = self.inbound.as_ref()?;
+        let captions = req
+            .data
+            .get("attachments")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.get("caption").and_then(Value::as_str));
+        let texts = req.data.get("text").and_then(Value::as_str).into_iter();
+        for text in texts.chain(captions) {
+            if let Err(reason) = stage.run(&req.thread_id, text).await {
+                warn!("Blocked message in {}: {}", req.thread_id, reason);
+                return Some(stage.blocked_reply(reason));
+            }
+        }
+        None
+    }
+
+    /// Whether replies are checked. Streamed replies are then held back until they are
+    /// complete.
+    pub fn checks_replies(&self) -> bool {
+        self.outbound.is_some()
+    }
+
+    /// Checks everything in a reply that reaches the user. A blocked `response` or part
+    /// replaces the whole reply with the canned one; blocked `schedule` entries are dropped.
+    pub async fn screen_reply(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        let Some(stage) = &self.outbound else {
+            return;
+        };
+        let mut blocked = None;
+        for text in res.response.iter_mut().chain(res.parts.iter_mut()) {
+            match stage.run(thread_id, text).await {
+                Ok(checked) => *text = checked,
+                Err(reason) => {
+                    blocked = Some(reason);
+                    break;
+                }
+            }
+        }
+        if let Some(reason) = blocked {
+            warn!("Blocked reply in {}: {}", thread_id, reason);
+            let replacement = stage.blocked_reply(reason);
+            res.next_step = replacement.next_step;
+            res.next_step_reason = replacement.next_step_reason;
+            res.response = replacement.response;
+            res.parts.clear();
+        }
+
+        let mut schedule = Vec::with_capacity(res.schedule.len());
+        for mut entry in std::mem::take(&mut res.schedule) {
+            match stage.run(thread_id, &entry.text).await {
+                Ok(checked) => {
+                    entry.text = checked;
+                    schedule.push(entry);
+                }
+                Err(reason) => warn!("Dropped scheduled reply in {}: {}", thread_id, reason),
+            }
+        }
+        res.schedule = schedule;
+    }
+}
+
+/// Lowercased words, punctuation dropped.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,427 @@
+use crate::{config::RedactionConfig, models::ai::LlmApiResponse};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+// Above this many threads, vaults unused for `VAULT_TTL` are dropped on the next call.
+const PRUNE_THRESHOLD: usize = 10_000;
+const VAULT_TTL: Duration = Duration::from_secs(24 * 3600);
+
+type Validator = fn(&str) -> bool;
+
+struct Detector {
+    label: String,
+    regex: Regex,
+    /// Built-in detectors skip matches glued to other letters or digits and validate them
+    builtin: bool,
+    validate: Validator,
+}
+
+// Tokens handed out on one thread, mapped back to the value as first seen.
+struct Vault {
+    tokens: HashMap<String, String>,
+    used: Instant,
+}
+
+/// Masks personal data in user content before it goes to the AI backend. Reversible
+/// tokens (`[CPF_3f9a12bc]`) are derived from the thread and the value with a keyed hash,
+/// so the same value always gets the same token and a token never stands for two values,
+/// even after a restart. Replies are re-hydrated from an in-memory map, so only tokens
+/// handed out since the last restart are put back.
+pub struct Redactor {
+    detectors: Vec<Detector>,
+    reversible: bool,
+    key: Vec<u8>,
+    token: Regex,
+    vaults: Mutex<HashMap<String, Vault>>,
+}
+
+impl Redactor {
+    /// `None` while no detector or pattern is configured.
+    pub fn new(config: &RedactionConfig) -> Result<Option<Self>, String> {
+        if config.detectors.is_empty() && config.patterns.is_empty() {
+            return Ok(None);
+        }
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|err| format!("Invalid PII pattern {pattern}: {err}"))
+        };
+
+        // Custom patterns first, so they win over the generic detectors.
+        let mut detectors = Vec::new();
+        for pattern in &config.patterns {
+            detectors.push(Detector {
+                label: pattern
+                    .name
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect(),
+                regex: compile(&pattern.pattern)?,
+                builtin: false,
+                validate: |_| true,
+            });
+        }
+        // CNPJ before CPF and cards before phones: the longer numbers go first.
+        let builtins: [(&str, &str, Validator); 5] = [
+            ("cnpj", r"\d{2}\.?\d{3}\.?\d{3}/?\d{4}-?\d{2}", valid_cnpj),
+            ("cpf", r"\d{3}\.?\d{3}\.?\d{3}-?\d{2}", valid_cpf),
+            ("card", r"\d(?:[ -]?\d){12,18}", valid_card),
+            (
+                "email",
+                r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+                |_| true,
+            ),
+            (
+                "phone",
+                r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,3}\)[\s.-]?|\d{2,3}[\s.-]?)?\d{4,5}[\s.-]?\d{4}",
+                valid_phone,
+            ),
+        ];
+        for (name, pattern, validate) in builtins {
+            if config.detectors.iter().any(|d| d == name) {
+                detectors.push(Detector {
+                    label: name.to_uppercase(),
+                    regex: compile(pattern)?,
+                    builtin: true,
+                    validate,
+                });
+            }
+        }
+
+        let key = match &config.token_key {
+            Some(key) => key.as_bytes().to_vec(),
+            None => {
+                if config.reversible {
+                    warn!("PII_TOKEN_KEY is unset; PII tokens change after a restart");
+                }
+                rand::random::<[u8; 32]>().to_vec()
+            }
+        };
+
+        Ok(Some(Self {
+            detectors,
+            reversible: config.reversible,
+            key,
+            token: compile(r"\[[A-Z0-9_]+_[0-9a-f]{8}\]")?,
+            vaults: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn redact(&self, thread_id: &str, text: &str) -> String {
+        let mut vaults = self.vaults.lock().expect("redactor poisoned");
+        if vaults.len() > PRUNE_THRESHOLD {
+            vaults.retain(|_, vault| vault.used.elapsed() < VAULT_TTL);
+        }
+        let vault = vaults
+            .entry(thread_id.to_string())
+            .or_insert_with(|| Vault {
+                tokens: HashMap::new(),
+                used: Instant::now(),
+            });
+        vault.used = Instant::now();
+
+        // All matches are taken from the original text and substituted in one go, so no
+        // detector sees the tokens of another. Earlier detectors win overlaps.
+        let mut spans: Vec<(usize, usize, &Detector)> = Vec::new();
+        for detector in &self.detectors {
+            for found in detector.regex.find_iter(text) {
+                let glued = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+                if detector.builtin
+                    && (glued(text[..found.start()].chars().next_back())
+                        || glued(text[found.end()..].chars().next())
+                        || !(detector.validate)(found.as_str()))
+                {
+                    continue;
+                }
+                let overlaps = spans
+                    .iter()
+                    .any(|&(start, end, _)| found.start() < end && start < found.end());
+                if !overlaps {
+                    spans.push((found.start(), found.end(), detector));
+                }
+            }
+        }
+        if spans.is_empty() {
+            return text.to_string();
+        }
+        spans.sort_by_key(|&(start, _, _)| start);
+
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for &(start, end, detector) in &spans {
+            out.push_str(&text[last..start]);
+            out.push_str(&self.token_for(thread_id, vault, detector, &text[start..end]));
+            last = end;
+        }
+        out.push_str(&text[last..]);
+        debug!("Redacted {} values for {}", spans.len(), thread_id);
+        out
+    }
+
+    fn token_for(
+        &self,
+        thread_id: &str,
+        vault: &mut Vault,
+        detector: &Detector,
+        value: &str,
+    ) -> String {
+        let label = &detector.label;
+        if !self.reversible {
+            return format!("[{label}]");
+        }
+        // `529.982.247-25` and `52998224725` are the same CPF.
+        let canonical = match (detector.builtin, label.as_str()) {
+            (true, "EMAIL") => value.to_lowercase(),
+            (true, _) => value.chars().filter(char::is_ascii_digit).collect(),
+            (false, _) => value.to_string(),
+        };
+        let mac = Hmac::<Sha256>::new_from_slice(&self.key)
+            .expect("HMAC takes keys of any length")
+            .chain_update(thread_id)
+            .chain_update([0])
+            .chain_update(label)
+            .chain_update([0])
+            .chain_update(canonical)
+            .finalize()
+            .into_bytes();
+        let token = format!("[{label}_{}]", hex::encode(&mac[..4]));
+        vault
+            .tokens
+            .entry(token.clone())
+            .or_insert_with(|| value.to_string());
+        token
+    }
+
+    /// Puts the original values back in place of the thread's tokens. Unknown tokens are
+    /// left as they are.
+    pub fn rehydrate(&self, thread_id: &str, text: &str) -> String {
+        if !self.reversible {
+            return text.to_string();
+        }
+        let vaults = self.vaults.lock().expect("redactor poisoned");
+        let Some(vault) = vaults.get(thread_id) else {
+            return text.to_string();
+        };
+        self.token
+            .replace_all(text, |caps: &regex::Captures| {
+                vault
+                    .tokens
+                    .get(&caps[0])
+                    .cloned()
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// Redacts the user content of an `InputRequest.data`: `text` and attachment captions.
+    /// Ids and other metadata are left alone.
+    pub fn redact_data(&self, thread_id: &str, data: &mut Value) {
+        if let Some(text) = data.get_mut("text") {
+            if let Some(raw) = text.as_str() {
+                *text = Value::String(self.redact(thread_id, raw));
+            }
+        }
+        if let Some(attachments) = data.get_mut("attachments").and_then(Value::as_array_mut) {
+            for caption in attachments.iter_mut().filter_map(|a| a.get_mut("caption")) {
+                if let Some(raw) = caption.as_str() {
+                    *caption = Value::String(self.redact(thread_id, raw));
+                }
+            }
+        }
+    }
+
+    /// Re-hydrates everything in a reply that reaches the user.
+    pub fn rehydrate_response(&self, thread_id: &str, res: &mut LlmApiResponse) {
+        if let Some(response) = res.response.as_mut() {
+            *response = self.rehydrate(thread_id, response);
+        }
+        for part in &mut res.parts {
+            *part = self.rehydrate(thread_id, part);
+        }
+        for entry in &mut res.schedule {
+            entry.text = self.rehydrate(thread_id, &entry.text);
+        }
+    }
+
+    /// How much of the start of a streamed text can be re-hydrated now: everything but a
+    /// token that may still be cut off at the end.
+    pub fn complete_prefix(text: &str) -> usize {
+        match text.rfind('[') {
+            Some(open) if !text[open..].contains(']') && text.len() - open < 40 => open,
+            _ => text.len(),
+        }
+    }
+}
+
+fn digits(value: &str) -> Vec<u32> {
+    value.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn all_same(digits: &[u32]) -> bool {
+    digits.windows(2).all(|w| w[0] == w[1])
+}
+
+fn valid_cpf(value: &str) -> bool {
+    let d = digits(value);
+    if d.len() != 11 || all_same(&d) {
+        return false;
+    }
+    let check = |len: usize| {
+        let sum: u32 = d[..len]
+            .iter()
+            .zip((2..=len as u32 + 1).rev())
+            .map(|(digit, weight)| digit * weight)
+            .sum();
+        (sum * 10 % 11) % 10
+    };
+    check(9) == d[9] && check(10) == d[10]
+}
+
+fn valid_cnpj(value: &str) -> bool {
+    let d = digits(value);
+    if d.len() != 14 || all_same(&d) {
+        return false;
+    }
+    const WEIGHTS: [u32; 13] = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+    let check = |len: usize| {
+        let sum: u32 = d[..len]
+            .iter()
+            .zip(&WEIGHTS[13 - len..])
+            .map(|(digit, weight)| digit * weight)
+            .sum();
+        match sum % 11 {
+            0 | 1 => 0,
+            rest => 11 - rest,
+        }
+    };
+    check(12) == d[12] && check(13) == d[13]
+}
+
+/// Luhn checksum over 13 to 19 digits.
+fn valid_card(value: &str) -> bool {
+    let d = digits(value);
+    if !(13..=19).contains(&d.len()) {
+        return false;
+    }
+    let sum: u32 = d
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn valid_phone(value: &str) -> bool {
+    (8..=15).contains(&digits(value).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpf_needs_valid_check_digits() {
+        assert!(valid_cpf("529.982.247-25"));
+        assert!(valid_cpf("52998224725"));
+        assert!(!valid_cpf("529.982.247-26"));
+        assert!(!valid_cpf("111.111.111-11"));
+        assert!(!valid_cpf("5299822472"));
+    }
+
+    #[test]
+    fn cnpj_needs_valid_check_digits() {
+        assert!(valid_cnpj("11.222.333/0001-81"));
+        assert!(valid_cnpj("11222333000181"));
+        assert!(!valid_cnpj("11.222.333/0001-82"));
+        assert!(!valid_cnpj("00.000.000/0000-00"));
+        assert!(!valid_cnpj("1122233300018"));
+    }
+
+    #[test]
+    fn card_needs_the_luhn_check() {
+        assert!(valid_card("4111 1111 1111 1111"));
+        assert!(valid_card("5500-0000-0000-0004"));
+        assert!(!valid_card("4111 1111 1111 1112"));
+        assert!(!valid_card("411111111111"));
+    }
+
+    #[test]
+    fn complete_prefix_holds_back_a_cut_token() {
+        assert_eq!(Redactor::complete_prefix("Your CPF is [CPF_3f"), 12);
+        assert_eq!(Redactor::complete_prefix("Your CPF is [CPF_3f9a12bc]."), 27);
+        assert_eq!(Redactor::complete_prefix("no tokens here"), 14);
+        let long = format!("a [{}", "x".repeat(50));
+        assert_eq!(Redactor::complete_prefix(&long), long.len());
+    }
+
+    fn redactor(key: &str) -> Redactor {
+        Redactor::new(&RedactionConfig {
+            detectors: vec!["cpf".to_string(), "phone".to_string()],
+            patterns: Vec::new(),
+            reversible: true,
+            token_key: Some(key.to_string()),
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn tokens_depend_on_value_and_thread_only() {
+        let first = redactor("key").redact("t1", "529.982.247-25");
+        // A fresh redactor (a restart) hands out the same token, whatever the formatting.
+        assert_eq!(redactor("key").redact("t1", "52998224725"), first);
+        assert_ne!(redactor("key").redact("t2", "529.982.247-25"), first);
+        assert_ne!(redactor("key").redact("t1", "111.444.777-35"), first);
+    }
+
+    #[test]
+    fn tokens_are_put_back() {
+        let redactor = redactor("key");
+        let token = redactor.redact("t1", "529.982.247-25");
+        assert!(token.starts_with("[CPF_"));
+        let reply = format!("Got {token}, thanks");
+        assert_eq!(
+            redactor.rehydrate("t1", &reply),
+            "Got 529.982.247-25, thanks"
+        );
+        assert_eq!(redactor.rehydrate("t2", &reply), reply);
+    }
+
+    #[test]
+    fn tokens_with_digit_suffixes_are_not_redacted_again() {
+        // Find a key under which the CPF's token ends in 8 digits, which the phone detector
+        // would take for a number.
+        let (redactor, token) = (0..10_000)
+            .map(|n| redactor(&format!("key{n}")))
+            .find_map(|redactor| {
+                let token = redactor.redact("t1", "529.982.247-25");
+                let suffix = &token["[CPF_".len()..token.len() - 1];
+                suffix
+                    .chars()
+                    .all(|c| c.is_ascii_digit())
+                    .then_some((redactor, token))
+            })
+            .unwrap();
+        let redacted = redactor.redact("t1", "cpf 529.982.247-25, phone 11 98765-4321");
+        assert!(redacted.starts_with(&format!("cpf {token}, phone [PHONE_")));
+        assert_eq!(
+            redactor.rehydrate("t1", &redacted),
+            "cpf 529.982.247-25, phone 11 98765-4321"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    config::SignalConfig,
+    models::signal::{SignalReceipt, SignalReceived, SignalTextOut, SignalTyping},
+    utils::check_status,
+};
+use reqwest::{Method, Url};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct SignalClient {
+    http: reqwest::Client,
+    config: Arc<SignalConfig>,
+}
+
+impl SignalClient {
+    pub fn new(config: SignalConfig, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            config: Arc::new(config),
+        }
+    }
+
+    pub fn config(&self) -> &SignalConfig {
+        &self.config
+    }
+
+    /// Polls pending messages for the configured number, waiting up to `receive_timeout_secs`.
+    pub async fn receive(&self) -> Result<Vec<SignalReceived>, String> {
+        let timeout = self.config.receive_timeout_secs;
+        let mut url = self.endpoint(&["v1", "receive", &self.config.number])?;
+        url.query_pairs_mut()
+            .append_pair("timeout", &timeout.to_string());
+
+        let res = self
+            .http
+            .get(url)
+            .timeout(Duration::from_secs(timeout + 30))
+            .send()
+            .await
+            .map_err(|err| format!("Failed to receive Signal messages: {err}"))?;
+        let res = check_status(res, "Signal receive").await?;
+        res.json::<Vec<SignalReceived>>()
+            .await
+            .map_err(|err| format!("Failed to parse Signal receive response: {err}"))
+    }
+
+    /// `recipient` is a phone number, an account uuid, or a `group.<id>` identifier.
+    pub async fn send_text_message(&self, recipient: &str, body: &str) -> Result<(), String> {
+        let url = self.endpoint(&["v2", "send"])?;
+        let payload = SignalTextOut {
+            message: body.to_string(),
+            number: self.config.number.clone(),
+            recipients: vec![recipient.to_string()],
+        };
+
+        let res = self
+            .http
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send Signal message: {err}"))?;
+        check_status(res, "Signal send message").await?;
+        Ok(())
+    }
+
+    pub async fn set_typing(&self, recipient: &str, typing: bool) -> Result<(), String> {
+        let url = self.endpoint(&["v1", "typing-indicator", &self.config.number])?;
+        let method = if typing { Method::PUT } else { Method::DELETE };
+        let payload = SignalTyping {
+            recipient: recipient.to_string(),
+        };
+
+        let res = self
+            .http
+            .request(method, url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| format!("Failed to update Signal typing indicator: {err}"))?;
+        check_status(res, "Signal typing indicator").await?;
+        Ok(())
+    }
+
+    pub async fn send_read_receipt(&self, recipient: &str, timestamp: i64) -> Result<(), String> {
+        let url = self.endpoint(&["v1", "receipts", &self.config.number])?;
+        let payload = SignalReceipt {
+            receipt_type: "read".to_string(),
+            recipient: recipient.to_string(),
+            timestamp,
+        };
+
+        let res = self
+            .http
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send Signal read receipt: {err}"))?;
+        check_status(res, "Signal read receipt").await?;
+        Ok(())
+    }
+
+    /// Download URL for an attachment, so the AI can fetch it if it needs to.
+    pub fn attachment_url(&self, attachment_id: &str) -> Result<Url, String> {
+        self.endpoint(&["v1", "attachments", attachment_id])
+    }
+
+    fn endpoint(&self, segments: &[&str]) -> Result<Url, String> {
+        let mut url = self.config.api_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| "Signal API URL cannot be a base".to_string())?
+            .pop_if_empty()
+            .extend(segments);
+        Ok(url)
+    }
+}
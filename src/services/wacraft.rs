@@ -1,6 +1,7 @@
 use crate::config::WacraftConfig;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
@@ -21,13 +22,75 @@ impl WacraftClient {
     }
 
     pub async fn send_text_message(&self, wa_id: &str, body: &str) -> Result<(), String> {
+        let mut sender_data = SenderData::new("text");
+        sender_data.text = Some(TextBody {
+            body: body.to_string(),
+        });
+        self.send(wa_id, sender_data).await
+    }
+
+    /// Sends a file by link; the message type (`image`, `video`, `audio` or `document`)
+    /// follows the MIME type.
+    pub async fn send_media_message(
+        &self,
+        wa_id: &str,
+        link: &str,
+        mime_type: &str,
+        filename: Option<&str>,
+        caption: Option<&str>,
+    ) -> Result<(), String> {
+        let message_type = match mime_type.split('/').next() {
+            Some("image") => "image",
+            Some("video") => "video",
+            Some("audio") => "audio",
+            _ => "document",
+        };
+        let media = MediaBody {
+            link: link.to_string(),
+            // WhatsApp rejects captions on audio and filenames on anything but documents.
+            caption: caption
+                .filter(|_| message_type != "audio")
+                .map(str::to_string),
+            filename: filename
+                .filter(|_| message_type == "document")
+                .map(str::to_string),
+        };
+        let mut sender_data = SenderData::new(message_type);
+        match message_type {
+            "image" => sender_data.image = Some(media),
+            "video" => sender_data.video = Some(media),
+            "audio" => sender_data.audio = Some(media),
+            _ => sender_data.document = Some(media),
+        }
+        self.send(wa_id, sender_data).await
+    }
+
+    pub async fn send_template_message(
+        &self,
+        wa_id: &str,
+        name: &str,
+        language: &str,
+        components: Vec<Value>,
+    ) -> Result<(), String> {
+        let mut sender_data = SenderData::new("template");
+        sender_data.template = Some(TemplateBody {
+            name: name.to_string(),
+            language: TemplateLanguage {
+                code: language.to_string(),
+            },
+            components,
+        });
+        self.send(wa_id, sender_data).await
+    }
+
+    async fn send(&self, wa_id: &str, mut sender_data: SenderData) -> Result<(), String> {
         let token = self.get_valid_token().await?;
         let contact = self
             .fetch_contact(&token, wa_id)
             .await?
             .ok_or_else(|| format!("Wacraft contact not found for wa_id {wa_id}"))?;
 
-        let destination_wa_id = contact
+        sender_data.to = contact
             .product_details
             .as_ref()
             .and_then(|details| details.wa_id.clone())
@@ -35,15 +98,7 @@ impl WacraftClient {
 
         let payload = SendMessageRequest {
             to_id: contact.id,
-            sender_data: SenderData {
-                messaging_product: "whatsapp".to_string(),
-                recipient_type: Some("individual".to_string()),
-                message_type: "text".to_string(),
-                to: destination_wa_id,
-                text: Some(TextBody {
-                    body: body.to_string(),
-                }),
-            },
+            sender_data,
         };
 
         let url = {
@@ -257,6 +312,56 @@ struct SenderData {
     to: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     text: Option<TextBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<MediaBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video: Option<MediaBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio: Option<MediaBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    document: Option<MediaBody>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<TemplateBody>,
+}
+
+impl SenderData {
+    /// An individual WhatsApp message of `message_type`; `to` is filled in by `send`.
+    fn new(message_type: &str) -> Self {
+        Self {
+            messaging_product: "whatsapp".to_string(),
+            recipient_type: Some("individual".to_string()),
+            message_type: message_type.to_string(),
+            to: String::new(),
+            text: None,
+            image: None,
+            video: None,
+            audio: None,
+            document: None,
+            template: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MediaBody {
+    link: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caption: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TemplateBody {
+    name: String,
+    language: TemplateLanguage,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    components: Vec<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct TemplateLanguage {
+    code: String,
 }
 
 #[derive(Debug, Serialize)]
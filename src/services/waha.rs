@@ -1,6 +1,6 @@
 use crate::{
     config::Config,
-    models::waha::{WahaSeen, WahaTextOut, WahaTyping},
+    models::waha::{WahaFileOut, WahaSeen, WahaTextOut, WahaTyping},
 };
 
 pub async fn send_text_message(
@@ -29,6 +29,34 @@ pub async fn send_text_message(
     Ok(())
 }
 
+/// Images go through `/api/sendImage`, anything else through `/api/sendFile`.
+pub async fn send_file_message(
+    http: &reqwest::Client,
+    cfg: &Config,
+    payload: WahaFileOut,
+) -> Result<(), String> {
+    let path = if payload.file.mimetype.starts_with("image/") {
+        "/api/sendImage"
+    } else {
+        "/api/sendFile"
+    };
+    let url = cfg.waha_base_url.join(path).map_err(|e| e.to_string())?;
+
+    let mut req = http.post(url).json(&payload);
+    if let Some(api_key) = &cfg.waha_api_key_plain {
+        req = req.header("X-Api-Key", api_key);
+    }
+
+    let res = req
+        .send()
+        .await
+        .map_err(|e| format!("request error: {e}"))?;
+    if !res.status().is_success() {
+        return Err(format!("waha status {}", res.status()));
+    }
+    Ok(())
+}
+
 pub async fn start_typing(
     http: &reqwest::Client,
     cfg: &Config,
@@ -0,0 +1,130 @@
+use crate::{config::WebChatConfig, models::webchat::WebChatFrame};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Delivers frames to web-chat sockets, buffering them while a session has no socket attached.
+#[derive(Clone)]
+pub struct WebChatHub {
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    ttl: Duration,
+    buffer_size: usize,
+}
+
+struct Session {
+    connection: Option<Connection>,
+    buffer: VecDeque<WebChatFrame>,
+    last_seen: Instant,
+}
+
+struct Connection {
+    id: Uuid,
+    tx: mpsc::UnboundedSender<WebChatFrame>,
+}
+
+/// A socket attached to a session. Frames for the session arrive on `rx`.
+pub struct Attachment {
+    pub session: String,
+    pub connection_id: Uuid,
+    pub rx: mpsc::UnboundedReceiver<WebChatFrame>,
+}
+
+impl WebChatHub {
+    pub fn new(cfg: &WebChatConfig) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            ttl: Duration::from_secs(cfg.session_ttl_secs),
+            buffer_size: cfg.buffer_size,
+        }
+    }
+
+    /// Attaches a socket to `session` if it is still known, otherwise opens a new session.
+    /// Frames buffered while the session was detached are queued on the new receiver first.
+    /// A previous socket of the same session is replaced (its receiver closes).
+    pub fn attach(&self, session: Option<&str>) -> Attachment {
+        let mut sessions = self.sessions.lock().expect("webchat sessions poisoned");
+        self.evict_expired(&mut sessions);
+
+        let token = session
+            .filter(|token| sessions.contains_key(*token))
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+        let entry = sessions.entry(token.clone()).or_insert_with(|| Session {
+            connection: None,
+            buffer: VecDeque::new(),
+            last_seen: Instant::now(),
+        });
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        for frame in entry.buffer.drain(..) {
+            let _ = tx.send(frame);
+        }
+
+        let connection_id = Uuid::new_v4();
+        entry.connection = Some(Connection {
+            id: connection_id,
+            tx,
+        });
+        entry.last_seen = Instant::now();
+
+        Attachment {
+            session: token,
+            connection_id,
+            rx,
+        }
+    }
+
+    /// Marks the socket as gone; later frames are buffered until the next `attach`.
+    pub fn detach(&self, session: &str, connection_id: Uuid) {
+        let mut sessions = self.sessions.lock().expect("webchat sessions poisoned");
+        if let Some(entry) = sessions.get_mut(session) {
+            if entry.connection.as_ref().map(|c| c.id) == Some(connection_id) {
+                entry.connection = None;
+                entry.last_seen = Instant::now();
+            }
+        }
+        self.evict_expired(&mut sessions);
+    }
+
+    /// Sends a frame to the attached socket, or buffers it for the next reconnect.
+    pub fn send(&self, session: &str, frame: WebChatFrame) {
+        let mut sessions = self.sessions.lock().expect("webchat sessions poisoned");
+        self.evict_expired(&mut sessions);
+        let Some(entry) = sessions.get_mut(session) else {
+            debug!("Dropping frame for unknown web-chat session {}", session);
+            return;
+        };
+
+        let frame = match entry.connection.as_ref() {
+            Some(connection) => match connection.tx.send(frame) {
+                Ok(()) => return,
+                Err(err) => {
+                    entry.connection = None;
+                    err.0
+                }
+            },
+            None => frame,
+        };
+
+        // Typing frames are only meaningful live; don't replay them on reconnect.
+        if self.buffer_size == 0 || matches!(frame, WebChatFrame::Typing { .. }) {
+            return;
+        }
+        if entry.buffer.len() >= self.buffer_size {
+            entry.buffer.pop_front();
+        }
+        entry.buffer.push_back(frame);
+    }
+
+    /// Forgets detached sessions idle for longer than the TTL. Runs on every attach, detach and
+    /// send, so idle sessions don't pile up waiting for a new socket.
+    fn evict_expired(&self, sessions: &mut HashMap<String, Session>) {
+        sessions.retain(|_, session| {
+            session.connection.is_some() || session.last_seen.elapsed() < self.ttl
+        });
+    }
+}
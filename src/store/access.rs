@@ -0,0 +1,137 @@
+use super::open_sqlite;
+use crate::{
+    config::ControlStoreConfig,
+    models::access::{AccessListKind, AccessRule},
+};
+use rusqlite::{Connection, params};
+use std::sync::{Arc, Mutex};
+
+/// Allow and block list patterns added through the admin API.
+pub trait AccessStore: Send + Sync {
+    /// Returns `false` when the rule already existed.
+    fn add(&self, rule: &AccessRule) -> Result<bool, String>;
+    /// Returns `false` when there was no such rule.
+    fn remove(&self, rule: &AccessRule) -> Result<bool, String>;
+    /// Every rule, oldest first.
+    fn list(&self) -> Result<Vec<AccessRule>, String>;
+}
+
+pub fn build_access_store(config: &ControlStoreConfig) -> Result<Arc<dyn AccessStore>, String> {
+    Ok(match config {
+        ControlStoreConfig::Memory => Arc::new(MemoryAccessStore::default()),
+        ControlStoreConfig::Sqlite { path } => Arc::new(SqliteAccessStore::open(path)?),
+    })
+}
+
+#[derive(Default)]
+pub struct MemoryAccessStore {
+    rules: Mutex<Vec<AccessRule>>,
+}
+
+impl AccessStore for MemoryAccessStore {
+    fn add(&self, rule: &AccessRule) -> Result<bool, String> {
+        let mut rules = self.rules.lock().expect("access store poisoned");
+        if rules.contains(rule) {
+            return Ok(false);
+        }
+        rules.push(rule.clone());
+        Ok(true)
+    }
+
+    fn remove(&self, rule: &AccessRule) -> Result<bool, String> {
+        let mut rules = self.rules.lock().expect("access store poisoned");
+        let before = rules.len();
+        rules.retain(|r| r != rule);
+        Ok(rules.len() < before)
+    }
+
+    fn list(&self) -> Result<Vec<AccessRule>, String> {
+        Ok(self.rules.lock().expect("access store poisoned").clone())
+    }
+}
+
+pub struct SqliteAccessStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteAccessStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS access_rules (
+                provider TEXT NOT NULL,
+                session TEXT NOT NULL DEFAULT '',
+                list TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                PRIMARY KEY (provider, session, list, pattern)
+            );",
+        )
+        .map_err(|err| format!("Failed to create access_rules table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl AccessStore for SqliteAccessStore {
+    fn add(&self, rule: &AccessRule) -> Result<bool, String> {
+        let conn = self.conn.lock().expect("access store poisoned");
+        let added = conn
+            .execute(
+                "INSERT OR IGNORE INTO access_rules (provider, session, list, pattern)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    rule.provider,
+                    rule.session.as_deref().unwrap_or_default(),
+                    rule.list.as_str(),
+                    rule.pattern,
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(added > 0)
+    }
+
+    fn remove(&self, rule: &AccessRule) -> Result<bool, String> {
+        let conn = self.conn.lock().expect("access store poisoned");
+        let removed = conn
+            .execute(
+                "DELETE FROM access_rules
+                 WHERE provider = ?1 AND session = ?2 AND list = ?3 AND pattern = ?4",
+                params![
+                    rule.provider,
+                    rule.session.as_deref().unwrap_or_default(),
+                    rule.list.as_str(),
+                    rule.pattern,
+                ],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(removed > 0)
+    }
+
+    fn list(&self) -> Result<Vec<AccessRule>, String> {
+        let conn = self.conn.lock().expect("access store poisoned");
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT provider, session, list, pattern FROM access_rules ORDER BY rowid",
+            )
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                let session: String = row.get(1)?;
+                let list: String = row.get(2)?;
+                Ok(AccessRule {
+                    provider: row.get(0)?,
+                    session: (!session.is_empty()).then_some(session),
+                    list: if list == "deny" {
+                        AccessListKind::Deny
+                    } else {
+                        AccessListKind::Allow
+                    },
+                    pattern: row.get(3)?,
+                })
+            })
+            .map_err(|err| err.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,112 @@
+use super::open_sqlite;
+use crate::{
+    config::ControlStoreConfig,
+    models::consent::{ConsentRecord, ConsentStatus},
+};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Opt-out and opt-in records, one per provider and contact.
+pub trait ConsentStore: Send + Sync {
+    fn get(&self, provider: &str, chat_id: &str) -> Result<Option<ConsentRecord>, String>;
+    /// Replaces the contact's earlier record.
+    fn set(&self, record: &ConsentRecord) -> Result<(), String>;
+}
+
+pub fn build_consent_store(config: &ControlStoreConfig) -> Result<Arc<dyn ConsentStore>, String> {
+    Ok(match config {
+        ControlStoreConfig::Memory => Arc::new(MemoryConsentStore::default()),
+        ControlStoreConfig::Sqlite { path } => Arc::new(SqliteConsentStore::open(path)?),
+    })
+}
+
+#[derive(Default)]
+pub struct MemoryConsentStore {
+    records: Mutex<HashMap<(String, String), ConsentRecord>>,
+}
+
+impl ConsentStore for MemoryConsentStore {
+    fn get(&self, provider: &str, chat_id: &str) -> Result<Option<ConsentRecord>, String> {
+        let records = self.records.lock().expect("consent store poisoned");
+        Ok(records
+            .get(&(provider.to_string(), chat_id.to_string()))
+            .cloned())
+    }
+
+    fn set(&self, record: &ConsentRecord) -> Result<(), String> {
+        let mut records = self.records.lock().expect("consent store poisoned");
+        records.insert(
+            (record.provider.clone(), record.chat_id.clone()),
+            record.clone(),
+        );
+        Ok(())
+    }
+}
+
+pub struct SqliteConsentStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteConsentStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS consent (
+                provider TEXT NOT NULL,
+                chat_id TEXT NOT NULL,
+                status TEXT NOT NULL,
+                keyword TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (provider, chat_id)
+            );",
+        )
+        .map_err(|err| format!("Failed to create consent table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl ConsentStore for SqliteConsentStore {
+    fn get(&self, provider: &str, chat_id: &str) -> Result<Option<ConsentRecord>, String> {
+        let conn = self.conn.lock().expect("consent store poisoned");
+        conn.query_row(
+            "SELECT status, keyword, updated_at FROM consent WHERE provider = ?1 AND chat_id = ?2",
+            params![provider, chat_id],
+            |row| {
+                let status: String = row.get(0)?;
+                Ok(ConsentRecord {
+                    provider: provider.to_string(),
+                    chat_id: chat_id.to_string(),
+                    status: if status == "opted_out" {
+                        ConsentStatus::OptedOut
+                    } else {
+                        ConsentStatus::OptedIn
+                    },
+                    keyword: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|err| err.to_string())
+    }
+
+    fn set(&self, record: &ConsentRecord) -> Result<(), String> {
+        let conn = self.conn.lock().expect("consent store poisoned");
+        conn.execute(
+            "INSERT OR REPLACE INTO consent (provider, chat_id, status, keyword, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                record.provider,
+                record.chat_id,
+                record.status.as_str(),
+                record.keyword,
+                record.updated_at,
+            ],
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use super::open_sqlite;
+use crate::{config::HistoryStoreConfig, models::ai::HistoryTurn};
+use rusqlite::{Connection, params};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Conversation turns per `thread_id`, oldest first.
+pub trait HistoryStore: Send + Sync {
+    fn load(&self, thread_id: &str) -> Result<Vec<HistoryTurn>, String>;
+    fn append(&self, thread_id: &str, turns: &[HistoryTurn]) -> Result<(), String>;
+    /// Replaces the oldest `count` turns of the thread with `turns`; turns stored after
+    /// them are kept (used after summarizing, which runs while new turns come in).
+    fn replace_oldest(
+        &self,
+        thread_id: &str,
+        count: usize,
+        turns: &[HistoryTurn],
+    ) -> Result<(), String>;
+}
+
+pub fn build_history_store(config: &HistoryStoreConfig) -> Result<Arc<dyn HistoryStore>, String> {
+    Ok(match config {
+        HistoryStoreConfig::Memory => Arc::new(MemoryHistoryStore::default()),
+        HistoryStoreConfig::Sqlite { path } => Arc::new(SqliteHistoryStore::open(path)?),
+    })
+}
+
+#[derive(Default)]
+pub struct MemoryHistoryStore {
+    threads: Mutex<HashMap<String, Vec<HistoryTurn>>>,
+}
+
+impl HistoryStore for MemoryHistoryStore {
+    fn load(&self, thread_id: &str) -> Result<Vec<HistoryTurn>, String> {
+        let threads = self.threads.lock().expect("history store poisoned");
+        Ok(threads.get(thread_id).cloned().unwrap_or_default())
+    }
+
+    fn append(&self, thread_id: &str, turns: &[HistoryTurn]) -> Result<(), String> {
+        let mut threads = self.threads.lock().expect("history store poisoned");
+        threads
+            .entry(thread_id.to_string())
+            .or_default()
+            .extend_from_slice(turns);
+        Ok(())
+    }
+
+    fn replace_oldest(
+        &self,
+        thread_id: &str,
+        count: usize,
+        turns: &[HistoryTurn],
+    ) -> Result<(), String> {
+        let mut threads = self.threads.lock().expect("history store poisoned");
+        let thread = threads.entry(thread_id.to_string()).or_default();
+        let count = count.min(thread.len());
+        thread.splice(..count, turns.iter().cloned());
+        Ok(())
+    }
+}
+
+pub struct SqliteHistoryStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteHistoryStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                thread_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+            CREATE INDEX IF NOT EXISTS history_thread ON history (thread_id, id);",
+        )
+        .map_err(|err| format!("Failed to create history table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl HistoryStore for SqliteHistoryStore {
+    fn load(&self, thread_id: &str) -> Result<Vec<HistoryTurn>, String> {
+        let conn = self.conn.lock().expect("history store poisoned");
+        let mut stmt = conn
+            .prepare_cached("SELECT role, content FROM history WHERE thread_id = ?1 ORDER BY id")
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map(params![thread_id], |row| {
+                Ok(HistoryTurn {
+                    role: row.get(0)?,
+                    content: row.get(1)?,
+                })
+            })
+            .map_err(|err| err.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    fn append(&self, thread_id: &str, turns: &[HistoryTurn]) -> Result<(), String> {
+        let mut conn = self.conn.lock().expect("history store poisoned");
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        insert_turns(&tx, thread_id, turns)?;
+        tx.commit().map_err(|err| err.to_string())
+    }
+
+    fn replace_oldest(
+        &self,
+        thread_id: &str,
+        count: usize,
+        turns: &[HistoryTurn],
+    ) -> Result<(), String> {
+        let mut conn = self.conn.lock().expect("history store poisoned");
+        let tx = conn.transaction().map_err(|err| err.to_string())?;
+        // Rows are ordered by id, so the kept ones are re-inserted after the new turns.
+        let kept = {
+            let mut stmt = tx
+                .prepare_cached(
+                    "SELECT role, content FROM history WHERE thread_id = ?1 ORDER BY id LIMIT -1 OFFSET ?2",
+                )
+                .map_err(|err| err.to_string())?;
+            let rows = stmt
+                .query_map(params![thread_id, count as i64], |row| {
+                    Ok(HistoryTurn {
+                        role: row.get(0)?,
+                        content: row.get(1)?,
+                    })
+                })
+                .map_err(|err| err.to_string())?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.to_string())?
+        };
+        tx.execute(
+            "DELETE FROM history WHERE thread_id = ?1",
+            params![thread_id],
+        )
+        .map_err(|err| err.to_string())?;
+        insert_turns(&tx, thread_id, turns)?;
+        insert_turns(&tx, thread_id, &kept)?;
+        tx.commit().map_err(|err| err.to_string())
+    }
+}
+
+fn insert_turns(conn: &Connection, thread_id: &str, turns: &[HistoryTurn]) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare_cached("INSERT INTO history (thread_id, role, content) VALUES (?1, ?2, ?3)")
+        .map_err(|err| err.to_string())?;
+    for turn in turns {
+        stmt.execute(params![thread_id, turn.role, turn.content])
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use rusqlite::Connection;
+
+pub mod access;
+pub mod consent;
+pub mod history;
+pub mod pause;
+pub mod schedule;
+pub mod threads;
+
+/// Opens (or creates) a SQLite database file. WAL keeps readers from blocking the writer.
+pub fn open_sqlite(path: &str) -> Result<Connection, String> {
+    let conn =
+        Connection::open(path).map_err(|err| format!("Failed to open SQLite db {path}: {err}"))?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(|err| format!("Failed to enable WAL on {path}: {err}"))?;
+    Ok(conn)
+}
+
+/// Runs a store call on the blocking pool, so SQLite I/O on the message path doesn't stall
+/// the async workers.
+pub async fn blocking<T, F>(call: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(call)
+        .await
+        .map_err(|err| format!("store task failed: {err}"))?
+}
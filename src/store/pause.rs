@@ -0,0 +1,221 @@
+use super::open_sqlite;
+use crate::{config::ControlStoreConfig, models::handoff::ChatPause};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Chats where AI replies are paused, keyed by provider, session and chat id (`*` for the
+/// whole session). Expired pauses are treated as absent.
+pub trait PauseStore: Send + Sync {
+    /// Pauses a chat, replacing any earlier pause of it.
+    fn pause(&self, pause: &ChatPause) -> Result<(), String>;
+    /// Returns `false` when the chat wasn't paused.
+    fn resume(&self, provider: &str, session: Option<&str>, chat_id: &str) -> Result<bool, String>;
+    fn get(
+        &self,
+        provider: &str,
+        session: Option<&str>,
+        chat_id: &str,
+    ) -> Result<Option<ChatPause>, String>;
+    /// Every pause that hasn't expired.
+    fn list(&self) -> Result<Vec<ChatPause>, String>;
+}
+
+pub fn build_pause_store(config: &ControlStoreConfig) -> Result<Arc<dyn PauseStore>, String> {
+    Ok(match config {
+        ControlStoreConfig::Memory => Arc::new(MemoryPauseStore::default()),
+        ControlStoreConfig::Sqlite { path } => Arc::new(SqlitePauseStore::open(path)?),
+    })
+}
+
+type PauseKey = (String, String, String);
+
+fn key(provider: &str, session: Option<&str>, chat_id: &str) -> PauseKey {
+    (
+        provider.to_string(),
+        session.unwrap_or_default().to_string(),
+        chat_id.to_string(),
+    )
+}
+
+#[derive(Default)]
+pub struct MemoryPauseStore {
+    pauses: Mutex<HashMap<PauseKey, (ChatPause, Option<i64>)>>,
+}
+
+impl PauseStore for MemoryPauseStore {
+    fn pause(&self, pause: &ChatPause) -> Result<(), String> {
+        let until = pause.until.as_deref().map(parse_time).transpose()?;
+        let mut pauses = self.pauses.lock().expect("pause store poisoned");
+        pauses.insert(
+            key(&pause.provider, pause.session.as_deref(), &pause.chat_id),
+            (pause.clone(), until),
+        );
+        Ok(())
+    }
+
+    fn resume(&self, provider: &str, session: Option<&str>, chat_id: &str) -> Result<bool, String> {
+        let mut pauses = self.pauses.lock().expect("pause store poisoned");
+        Ok(pauses.remove(&key(provider, session, chat_id)).is_some())
+    }
+
+    fn get(
+        &self,
+        provider: &str,
+        session: Option<&str>,
+        chat_id: &str,
+    ) -> Result<Option<ChatPause>, String> {
+        let mut pauses = self.pauses.lock().expect("pause store poisoned");
+        let key = key(provider, session, chat_id);
+        match pauses.get(&key) {
+            Some((_, Some(until))) if *until <= Utc::now().timestamp() => {
+                pauses.remove(&key);
+                Ok(None)
+            }
+            Some((pause, _)) => Ok(Some(pause.clone())),
+            None => Ok(None),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<ChatPause>, String> {
+        let mut pauses = self.pauses.lock().expect("pause store poisoned");
+        let now = Utc::now().timestamp();
+        pauses.retain(|_, (_, until)| until.is_none_or(|until| until > now));
+        let mut list: Vec<ChatPause> = pauses.values().map(|(pause, _)| pause.clone()).collect();
+        list.sort_by(|a, b| a.paused_at.cmp(&b.paused_at));
+        Ok(list)
+    }
+}
+
+pub struct SqlitePauseStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqlitePauseStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS paused_chats (
+                provider TEXT NOT NULL,
+                session TEXT NOT NULL DEFAULT '',
+                chat_id TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                paused_at INTEGER NOT NULL,
+                until INTEGER,
+                PRIMARY KEY (provider, session, chat_id)
+            );",
+        )
+        .map_err(|err| format!("Failed to create paused_chats table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl PauseStore for SqlitePauseStore {
+    fn pause(&self, pause: &ChatPause) -> Result<(), String> {
+        let paused_at = parse_time(&pause.paused_at)?;
+        let until = pause.until.as_deref().map(parse_time).transpose()?;
+        let conn = self.conn.lock().expect("pause store poisoned");
+        conn.execute(
+            "INSERT OR REPLACE INTO paused_chats
+             (provider, session, chat_id, reason, paused_at, until)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                pause.provider,
+                pause.session.as_deref().unwrap_or_default(),
+                pause.chat_id,
+                pause.reason,
+                paused_at,
+                until,
+            ],
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn resume(&self, provider: &str, session: Option<&str>, chat_id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().expect("pause store poisoned");
+        let removed = conn
+            .execute(
+                "DELETE FROM paused_chats WHERE provider = ?1 AND session = ?2 AND chat_id = ?3",
+                params![provider, session.unwrap_or_default(), chat_id],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(removed > 0)
+    }
+
+    fn get(
+        &self,
+        provider: &str,
+        session: Option<&str>,
+        chat_id: &str,
+    ) -> Result<Option<ChatPause>, String> {
+        let conn = self.conn.lock().expect("pause store poisoned");
+        let session = session.unwrap_or_default();
+        conn.execute(
+            "DELETE FROM paused_chats
+             WHERE provider = ?1 AND session = ?2 AND chat_id = ?3 AND until <= ?4",
+            params![provider, session, chat_id, Utc::now().timestamp()],
+        )
+        .map_err(|err| err.to_string())?;
+        conn.query_row(
+            &format!(
+                "SELECT {COLUMNS} FROM paused_chats
+                 WHERE provider = ?1 AND session = ?2 AND chat_id = ?3"
+            ),
+            params![provider, session, chat_id],
+            read_row,
+        )
+        .optional()
+        .map_err(|err| err.to_string())
+    }
+
+    fn list(&self) -> Result<Vec<ChatPause>, String> {
+        let conn = self.conn.lock().expect("pause store poisoned");
+        conn.execute(
+            "DELETE FROM paused_chats WHERE until <= ?1",
+            params![Utc::now().timestamp()],
+        )
+        .map_err(|err| err.to_string())?;
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "SELECT {COLUMNS} FROM paused_chats ORDER BY paused_at"
+            ))
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map([], read_row)
+            .map_err(|err| err.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+}
+
+const COLUMNS: &str = "provider, session, chat_id, reason, paused_at, until";
+
+fn read_row(row: &Row<'_>) -> rusqlite::Result<ChatPause> {
+    let session: String = row.get(1)?;
+    let paused_at: i64 = row.get(4)?;
+    let until: Option<i64> = row.get(5)?;
+    Ok(ChatPause {
+        provider: row.get(0)?,
+        session: (!session.is_empty()).then_some(session),
+        chat_id: row.get(2)?,
+        reason: row.get(3)?,
+        paused_at: format_time(paused_at),
+        until: until.map(format_time),
+    })
+}
+
+fn parse_time(value: &str) -> Result<i64, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.timestamp())
+        .map_err(|err| format!("invalid time '{value}': {err}"))
+}
+
+fn format_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, false)
+}
@@ -0,0 +1,189 @@
+use super::open_sqlite;
+use crate::models::messages::{OutboundContent, ScheduledMessage};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, Row, params};
+use std::sync::Mutex;
+
+const COLUMNS: &str =
+    "id, provider, session, recipient, content, send_at, status, attempts, last_error";
+
+/// Messages waiting for their time, keyed by id. Sent messages are deleted; messages that
+/// ran out of attempts stay as `failed` so they show up when listing the contact.
+pub struct ScheduleStore {
+    conn: Mutex<Connection>,
+}
+
+impl ScheduleStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS scheduled_messages (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                session TEXT,
+                recipient TEXT NOT NULL,
+                content TEXT NOT NULL,
+                send_at INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                created_at INTEGER NOT NULL DEFAULT (unixepoch())
+            );
+            CREATE INDEX IF NOT EXISTS scheduled_due ON scheduled_messages (status, send_at);
+            CREATE INDEX IF NOT EXISTS scheduled_contact
+                ON scheduled_messages (provider, recipient, send_at);",
+        )
+        .map_err(|err| format!("Failed to create scheduled_messages table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn insert(&self, message: &ScheduledMessage) -> Result<(), String> {
+        let content = serde_json::to_string(&message.content).map_err(|err| err.to_string())?;
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        conn.execute(
+            "INSERT INTO scheduled_messages (id, provider, session, recipient, content, send_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                message.id,
+                message.provider,
+                message.session,
+                message.to,
+                content,
+                parse_time(&message.send_at)?.timestamp(),
+            ],
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Drops a message that wasn't sent yet. Returns `false` for unknown ids.
+    pub fn cancel(&self, id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        let removed = conn
+            .execute("DELETE FROM scheduled_messages WHERE id = ?1", params![id])
+            .map_err(|err| err.to_string())?;
+        Ok(removed > 0)
+    }
+
+    /// Everything still scheduled (or failed) for one contact, soonest first.
+    pub fn list(&self, provider: &str, to: &str) -> Result<Vec<ScheduledMessage>, String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "SELECT {COLUMNS} FROM scheduled_messages
+                 WHERE provider = ?1 AND recipient = ?2 ORDER BY send_at, created_at"
+            ))
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map(params![provider, to], read_row)
+            .map_err(|err| err.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Pending messages whose time has come, oldest first.
+    pub fn due(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<ScheduledMessage>, String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "SELECT {COLUMNS} FROM scheduled_messages
+                 WHERE status = 'pending' AND send_at <= ?1
+                 ORDER BY send_at, created_at LIMIT ?2"
+            ))
+            .map_err(|err| err.to_string())?;
+        let rows = stmt
+            .query_map(params![now.timestamp(), limit], read_row)
+            .map_err(|err| err.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| err.to_string())
+    }
+
+    /// Leases a due message until `until`, so neither a later poll nor another instance on
+    /// the same file picks it up while it is being sent. Returns `false` when it was taken
+    /// (or cancelled) meanwhile. A message whose send never finished is due again at `until`.
+    pub fn claim(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<bool, String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        let claimed = conn
+            .execute(
+                "UPDATE scheduled_messages SET send_at = ?3
+                 WHERE id = ?1 AND status = 'pending' AND send_at <= ?2",
+                params![id, now.timestamp(), until.timestamp()],
+            )
+            .map_err(|err| err.to_string())?;
+        Ok(claimed > 0)
+    }
+
+    pub fn mark_sent(&self, id: &str) -> Result<(), String> {
+        self.cancel(id).map(|_| ())
+    }
+
+    /// Records a failed attempt. The message is tried again at `retry_at`, or marked
+    /// `failed` when `retry_at` is `None`.
+    pub fn mark_attempt_failed(
+        &self,
+        id: &str,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        match retry_at {
+            Some(at) => conn.execute(
+                "UPDATE scheduled_messages
+                 SET attempts = attempts + 1, last_error = ?2, send_at = ?3 WHERE id = ?1",
+                params![id, error, at.timestamp()],
+            ),
+            None => conn.execute(
+                "UPDATE scheduled_messages
+                 SET attempts = attempts + 1, last_error = ?2, status = 'failed' WHERE id = ?1",
+                params![id, error],
+            ),
+        }
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Pushes a message back without counting an attempt (e.g. the recipient's rate limit).
+    pub fn postpone(&self, id: &str, until: DateTime<Utc>) -> Result<(), String> {
+        let conn = self.conn.lock().expect("schedule store poisoned");
+        conn.execute(
+            "UPDATE scheduled_messages SET send_at = ?2 WHERE id = ?1",
+            params![id, until.timestamp()],
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
+
+fn read_row(row: &Row<'_>) -> rusqlite::Result<ScheduledMessage> {
+    let content: String = row.get(4)?;
+    let content = serde_json::from_str::<OutboundContent>(&content).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    let send_at: i64 = row.get(5)?;
+    Ok(ScheduledMessage {
+        id: row.get(0)?,
+        provider: row.get(1)?,
+        session: row.get(2)?,
+        to: row.get(3)?,
+        content,
+        send_at: DateTime::from_timestamp(send_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339_opts(SecondsFormat::Secs, false),
+        status: row.get(6)?,
+        attempts: row.get(7)?,
+        last_error: row.get(8)?,
+    })
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|err| format!("invalid time '{value}': {err}"))
+}
@@ -0,0 +1,106 @@
+use super::open_sqlite;
+use crate::config::ControlStoreConfig;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// How many times each thread was reset. The agent sees generation `n > 0` as the thread
+/// id `<thread_id>#<n>`, so a reset starts a fresh conversation on its side.
+pub trait ThreadStore: Send + Sync {
+    fn generation(&self, thread_id: &str) -> Result<u32, String>;
+    /// Starts a new generation of the thread and returns it.
+    fn bump(&self, thread_id: &str) -> Result<u32, String>;
+}
+
+pub fn build_thread_store(config: &ControlStoreConfig) -> Result<Arc<dyn ThreadStore>, String> {
+    Ok(match config {
+        ControlStoreConfig::Memory => Arc::new(MemoryThreadStore::default()),
+        ControlStoreConfig::Sqlite { path } => Arc::new(SqliteThreadStore::open(path)?),
+    })
+}
+
+/// The thread id the agent sees for the given generation.
+pub fn current_thread_id(thread_id: &str, generation: u32) -> String {
+    if generation == 0 {
+        thread_id.to_string()
+    } else {
+        format!("{thread_id}#{generation}")
+    }
+}
+
+/// The thread id without the `#<generation>` suffix added by [`current_thread_id`].
+pub fn base_thread_id(thread_id: &str) -> &str {
+    match thread_id.rsplit_once('#') {
+        Some((base, generation))
+            if !generation.is_empty() && generation.bytes().all(|b| b.is_ascii_digit()) =>
+        {
+            base
+        }
+        _ => thread_id,
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryThreadStore {
+    generations: Mutex<HashMap<String, u32>>,
+}
+
+impl ThreadStore for MemoryThreadStore {
+    fn generation(&self, thread_id: &str) -> Result<u32, String> {
+        let generations = self.generations.lock().expect("thread store poisoned");
+        Ok(generations.get(thread_id).copied().unwrap_or_default())
+    }
+
+    fn bump(&self, thread_id: &str) -> Result<u32, String> {
+        let mut generations = self.generations.lock().expect("thread store poisoned");
+        let generation = generations.entry(thread_id.to_string()).or_default();
+        *generation += 1;
+        Ok(*generation)
+    }
+}
+
+pub struct SqliteThreadStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteThreadStore {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = open_sqlite(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS thread_resets (
+                thread_id TEXT PRIMARY KEY,
+                generation INTEGER NOT NULL
+            );",
+        )
+        .map_err(|err| format!("Failed to create thread_resets table: {err}"))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl ThreadStore for SqliteThreadStore {
+    fn generation(&self, thread_id: &str) -> Result<u32, String> {
+        let conn = self.conn.lock().expect("thread store poisoned");
+        conn.query_row(
+            "SELECT generation FROM thread_resets WHERE thread_id = ?1",
+            params![thread_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map(Option::unwrap_or_default)
+        .map_err(|err| err.to_string())
+    }
+
+    fn bump(&self, thread_id: &str) -> Result<u32, String> {
+        let conn = self.conn.lock().expect("thread store poisoned");
+        conn.query_row(
+            "INSERT INTO thread_resets (thread_id, generation) VALUES (?1, 1)
+             ON CONFLICT(thread_id) DO UPDATE SET generation = generation + 1
+             RETURNING generation",
+            params![thread_id],
+            |row| row.get(0),
+        )
+        .map_err(|err| err.to_string())
+    }
+}
@@ -1 +1,3 @@
 pub mod mutex_swapper;
+pub mod rate_limiter;
+pub mod sent_messages;
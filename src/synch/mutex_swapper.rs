@@ -10,6 +10,13 @@ struct SwapperState<T: Eq + Hash> {
     ref_counts: HashMap<T, usize>,
 }
 
+// A key with a lock in flight, as seen by `snapshot`.
+pub struct KeyLockState<T> {
+    pub key: T,
+    pub locked: bool,
+    pub waiting: usize,
+}
+
 // The main MutexSwapper struct.
 pub struct MutexSwapper<T: Eq + Hash> {
     state: Mutex<SwapperState<T>>,
@@ -47,6 +54,28 @@ impl<T: Eq + Hash + Clone> MutexSwapper<T> {
         per_key_mutex.lock_owned().await
     }
 
+    // Lists the keys that are locked or being waited on. Each guard and each waiter holds
+    // a clone of the key's mutex, so anything above the map's own reference is in flight.
+    pub async fn snapshot(&self) -> Vec<KeyLockState<T>> {
+        let state = self.state.lock().await;
+        state
+            .mutexes
+            .iter()
+            .filter_map(|(key, mutex)| {
+                let holders = Arc::strong_count(mutex) - 1;
+                if holders == 0 {
+                    return None;
+                }
+                let locked = mutex.try_lock().is_err();
+                Some(KeyLockState {
+                    key: key.clone(),
+                    locked,
+                    waiting: holders - usize::from(locked),
+                })
+            })
+            .collect()
+    }
+
     // We don't need the `unlock` method anymore because the RAII guard
     // handles everything when it goes out of scope. For simplicity and
     // to prevent potential misuse, we can remove it.
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Above this many buckets, idle (full) ones are dropped on the next call.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// One token bucket per key: `burst` tokens, refilled at `per_minute`.
+pub struct RateLimiter<T: Eq + Hash> {
+    burst: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<T, Bucket>>,
+}
+
+impl<T: Eq + Hash + Clone> RateLimiter<T> {
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self {
+            burst: f64::from(burst.max(1)),
+            refill_per_sec: f64::from(per_minute) / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Takes one token for `key`. On `Err`, the caller should retry after the returned delay.
+    pub fn try_acquire(&self, key: &T) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("rate limiter poisoned");
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            let (burst, refill) = (self.burst, self.refill_per_sec);
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * refill < burst
+            });
+        }
+
+        let bucket = buckets.entry(key.clone()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.refill_per_sec <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / self.refill_per_sec,
+        ))
+    }
+}
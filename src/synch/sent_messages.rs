@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Remembers what we sent, keyed by chat and a hash of the text, so the provider's echo of
+// our own message (e.g. WAHA `fromMe`) can be told apart from one typed by a human.
+pub struct SentMessages {
+    ttl: Duration,
+    sent: Mutex<HashMap<(String, u64), Vec<Instant>>>,
+}
+
+impl SentMessages {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Call before sending: the echo can arrive before the send request returns.
+    pub fn record(&self, chat_id: &str, body: &str) {
+        let now = Instant::now();
+        let mut sent = self.sent.lock().expect("sent messages poisoned");
+        sent.retain(|_, times| {
+            times.retain(|t| now.duration_since(*t) < self.ttl);
+            !times.is_empty()
+        });
+        sent.entry((chat_id.to_string(), fingerprint(body)))
+            .or_default()
+            .push(now);
+    }
+
+    // Consumes one matching record; `true` means the message was ours.
+    pub fn take(&self, chat_id: &str, body: &str) -> bool {
+        let now = Instant::now();
+        let mut sent = self.sent.lock().expect("sent messages poisoned");
+        let key = (chat_id.to_string(), fingerprint(body));
+        let Some(times) = sent.get_mut(&key) else {
+            return false;
+        };
+        times.retain(|t| now.duration_since(*t) < self.ttl);
+        let found = !times.is_empty();
+        if found {
+            times.remove(0);
+        }
+        if times.is_empty() {
+            sent.remove(&key);
+        }
+        found
+    }
+}
+
+fn fingerprint(body: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    body.trim().hash(&mut hasher);
+    hasher.finish()
+}
@@ -1,4 +1,7 @@
-use crate::config::Config;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+
+use crate::config::{Config, GenericProviderConfig};
 
 pub fn thread_id_for_waha(cfg: &Config, user_id: &str) -> String {
     format!("{}{}", cfg.thread_prefix_waha, user_id)
@@ -7,3 +10,61 @@ pub fn thread_id_for_waha(cfg: &Config, user_id: &str) -> String {
 pub fn thread_id_for_wacraft(cfg: &Config, user_id: &str) -> String {
     format!("{}{}", cfg.thread_prefix_wacraft, user_id)
 }
+
+pub fn thread_id_for_matrix(cfg: &Config, room_id: &str) -> String {
+    format!("{}{}", cfg.thread_prefix_matrix, room_id)
+}
+
+pub fn thread_id_for_signal(cfg: &Config, chat_id: &str) -> String {
+    format!("{}{}", cfg.thread_prefix_signal, chat_id)
+}
+
+pub fn thread_id_for_chat(cfg: &Config, user_id: &str) -> String {
+    format!("{}{}", cfg.thread_prefix_chat, user_id)
+}
+
+pub fn thread_id_for_webchat(cfg: &Config, session: &str) -> String {
+    format!("{}{}", cfg.thread_prefix_webchat, session)
+}
+
+pub fn thread_id_for_generic(provider: &GenericProviderConfig, chat_id: &str) -> String {
+    match &provider.thread_prefix {
+        Some(prefix) => format!("{prefix}{chat_id}"),
+        None => format!("{}:{chat_id}", provider.name),
+    }
+}
+
+/// Checks a hex-encoded HMAC (`sha256` or `sha512`) of `body` in constant time.
+pub fn verify_hmac_hex(algorithm: &str, secret: &[u8], body: &[u8], signature_hex: &str) -> bool {
+    let Ok(signature) = hex::decode(signature_hex.trim()) else {
+        return false;
+    };
+
+    match algorithm.to_ascii_lowercase().as_str() {
+        "sha256" => Hmac::<Sha256>::new_from_slice(secret)
+            .map(|mac| mac.chain_update(body).verify_slice(&signature).is_ok())
+            .unwrap_or(false),
+        "sha512" => Hmac::<Sha512>::new_from_slice(secret)
+            .map(|mac| mac.chain_update(body).verify_slice(&signature).is_ok())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Passes successful responses through; other statuses become an error with the body.
+pub async fn check_status(res: reqwest::Response, what: &str) -> Result<reqwest::Response, String> {
+    if res.status().is_success() {
+        return Ok(res);
+    }
+    let status = res.status();
+    let body = res
+        .text()
+        .await
+        .unwrap_or_else(|_| "<body unavailable>".to_string());
+    Err(format!("{what} failed with status {status}: {body}"))
+}
+
+/// Compares secrets without short-circuiting on the first differing byte.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}